@@ -0,0 +1,37 @@
+use std::process::ExitCode;
+
+use flowy::{headless::{self, RunOptions, RunOutcome}, simulator::scenario::BuiltinScenario};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        print!("{}", headless::USAGE);
+        return ExitCode::SUCCESS;
+    }
+    if args.iter().any(|a| a == "--list-scenarios") {
+        for builtin in BuiltinScenario::ALL {
+            println!("{:<18} {}", builtin.key(), builtin.scenario().description);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let options = match RunOptions::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("flowy-cli: {e}\n\n{}", headless::USAGE);
+            return ExitCode::from(1);
+        }
+    };
+
+    let result = options.initial_state()
+        .and_then(|(mut simulator, dt)| headless::run(&mut simulator, dt, &options, &mut std::io::stdout().lock()));
+
+    match result {
+        Ok(RunOutcome::Completed { .. }) => ExitCode::SUCCESS,
+        Ok(RunOutcome::Diverged { .. }) => ExitCode::from(2),
+        Err(e) => {
+            eprintln!("flowy-cli: {e}");
+            ExitCode::from(1)
+        }
+    }
+}
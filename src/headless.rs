@@ -0,0 +1,357 @@
+use std::{error::Error, fs::{self, File}, io::{BufWriter, Write}, path::{Path, PathBuf}, str::FromStr};
+
+use crate::{
+    simulator::{simulator::Simulator, vtk::VtkSeries, arrays, scenario::Scenario, snapshot::Snapshot, diagnostics::DiagnosticsHistory},
+    visualize::{recording::{Recorder, RecordingFormat}, render::LayerStack, colormap::{ColorScale, Colormap, Normalization}}
+};
+
+pub const USAGE: &str = "\
+usage: flowy-cli [options]
+
+runs the simulation without a window, prints diagnostics and writes outputs
+
+initial state (default: the 20-cell demo setup)
+  --scenario FILE|NAME    set up from a TOML or JSON scenario or a built-in one, whose run
+                          length and outputs become the defaults for the options below
+  --list-scenarios        print the names of the built-in scenarios and exit
+  --snapshot FILE         start from a saved snapshot, using its time step
+  --resume FILE           continue from a checkpoint, using its time step
+
+run length (default: 100 steps)
+  --steps N               stop after N steps
+  --until T               stop once the simulation time reaches T
+  --dt DT                 time step
+  --projection            make the velocity field divergence free after every step
+  --no-projection         skip the pressure projection (default unless set by the scenario or checkpoint)
+  --max-speed V           treat face velocities above V as diverged (default 1e6)
+
+output
+  --print-every N         diagnostics line every N steps, 0 for none (default 10)
+  --output-every N        write the outputs below every N steps, 0 for only at the end (default 0)
+  --vtk DIR               .vti files and a .pvd collection
+  --npz DIR               all fields as fields_<step>.npz
+  --diagnostics FILE      diagnostics of every step as CSV
+  --checkpoint FILE       checkpoint path, see --checkpoint-every
+  --checkpoint-every N    write a checkpoint every N steps (default 100 with --checkpoint)
+  --record PATH           animated GIF (*.gif) or a directory of PNG frames
+  --frame-size PX         side length of recorded frames (default 512)
+  --frame-skip N          steps skipped between recorded frames (default 0)
+  --quiet                 only print the summary
+
+exit status: 0 when the run completes, 1 on invalid options or I/O errors, 2 on divergence (NaN or blow-up)
+";
+
+/// settings of one headless run
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOptions {
+    pub steps: Option<u32>,
+    pub until: Option<f64>,
+    pub dt: Option<f64>,
+    pub scenario: Option<Scenario>,
+    pub snapshot: Option<PathBuf>,
+    pub resume: Option<PathBuf>,
+    /// overrides the projection setting of the initial state
+    pub projection: Option<bool>,
+    pub max_speed: f64,
+
+    pub print_every: u32,
+    pub output_every: u32,
+    pub vtk: Option<PathBuf>,
+    pub npz: Option<PathBuf>,
+    pub diagnostics: Option<PathBuf>,
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_every: u32,
+    pub record: Option<PathBuf>,
+    pub frame_size: usize,
+    pub frame_skip: u32,
+    pub quiet: bool
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunOptions {
+    pub fn new() -> Self {
+        Self {
+            steps: None,
+            until: None,
+            dt: None,
+            scenario: None,
+            snapshot: None,
+            resume: None,
+            projection: None,
+            max_speed: 1e6,
+            print_every: 10,
+            output_every: 0,
+            vtk: None,
+            npz: None,
+            diagnostics: None,
+            checkpoint: None,
+            checkpoint_every: 100,
+            record: None,
+            frame_size: 512,
+            frame_skip: 0,
+            quiet: false
+        }
+    }
+
+    /// command line arguments without the program name; a scenario is loaded here so its settings
+    /// can be overridden by the other arguments
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self::new();
+        options.apply_args(args)?;
+
+        if let Some(scenario) = options.scenario.take() {
+            options = Self::from_scenario(scenario);
+            options.apply_args(args)?;
+        }
+        options.validate()?;
+
+        Ok(options)
+    }
+
+    /// the run described by a scenario file; its time step applies unless a snapshot or checkpoint brings one
+    pub fn from_scenario(scenario: Scenario) -> Self {
+        let (simulation, output) = (&scenario.simulation, &scenario.output);
+        Self {
+            steps: simulation.steps,
+            until: simulation.until,
+            output_every: output.every,
+            vtk: output.vtk.clone(),
+            npz: output.npz.clone(),
+            diagnostics: output.diagnostics.clone(),
+            checkpoint: output.checkpoint.clone(),
+            checkpoint_every: output.checkpoint_every,
+            record: output.record.clone(),
+            scenario: Some(scenario),
+            ..Self::new()
+        }
+    }
+
+    fn apply_args(&mut self, args: &[String]) -> Result<(), String> {
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().map(String::as_str).ok_or_else(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--scenario" => {
+                    let name = value()?;
+                    // already loaded on the second pass
+                    if self.scenario.is_none() {
+                        self.scenario = Some(Scenario::open(name).map_err(|e| format!("{name}: {e}"))?);
+                    }
+                },
+                "--snapshot" => self.snapshot = Some(PathBuf::from(value()?)),
+                "--resume" => self.resume = Some(PathBuf::from(value()?)),
+                "--steps" => self.steps = Some(number(arg, value()?)?),
+                "--until" => self.until = Some(number(arg, value()?)?),
+                "--dt" => self.dt = Some(number(arg, value()?)?),
+                "--projection" => self.projection = Some(true),
+                "--no-projection" => self.projection = Some(false),
+                "--max-speed" => self.max_speed = number(arg, value()?)?,
+                "--print-every" => self.print_every = number(arg, value()?)?,
+                "--output-every" => self.output_every = number(arg, value()?)?,
+                "--vtk" => self.vtk = Some(PathBuf::from(value()?)),
+                "--npz" => self.npz = Some(PathBuf::from(value()?)),
+                "--diagnostics" => self.diagnostics = Some(PathBuf::from(value()?)),
+                "--checkpoint" => self.checkpoint = Some(PathBuf::from(value()?)),
+                "--checkpoint-every" => self.checkpoint_every = number(arg, value()?)?,
+                "--record" => self.record = Some(PathBuf::from(value()?)),
+                "--frame-size" => self.frame_size = number(arg, value()?)?,
+                "--frame-skip" => self.frame_skip = number(arg, value()?)?,
+                "--quiet" => self.quiet = true,
+                _ => return Err(format!("unknown option {arg}"))
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&mut self) -> Result<(), String> {
+        if self.snapshot.is_some() && self.resume.is_some() {
+            return Err(String::from("--snapshot and --resume can't be combined"));
+        }
+        if self.dt.is_some_and(|dt| !(dt > 0.0 && dt.is_finite())) {
+            return Err(String::from("--dt must be positive"));
+        }
+        if self.until.is_some_and(|t| !t.is_finite()) {
+            return Err(String::from("--until must be finite"));
+        }
+        if self.checkpoint.is_some() && self.checkpoint_every == 0 {
+            return Err(String::from("--checkpoint-every must be positive"));
+        }
+        if !(16..=4096).contains(&self.frame_size) {
+            return Err(String::from("--frame-size must be between 16 and 4096"));
+        }
+        if self.steps.is_none() && self.until.is_none() {
+            self.steps = Some(100);
+        }
+        Ok(())
+    }
+
+    /// the simulator to start from and its time step: `dt` if given, else that of the
+    /// snapshot, checkpoint or scenario the run starts from
+    pub fn initial_state(&self) -> Result<(Simulator, f64), Box<dyn Error>> {
+        let (mut simulator, mut dt) = match &self.scenario {
+            Some(scenario) => (scenario.build()?, scenario.simulation.dt),
+            None => (Simulator::demo(), 0.2)
+        };
+
+        if let Some(path) = &self.snapshot {
+            let snapshot = Snapshot::load(path)?;
+            snapshot.apply(&mut simulator);
+            dt = snapshot.dt;
+        }
+        if let Some(path) = &self.resume {
+            dt = simulator.restore_checkpoint(path)?.unwrap_or(dt);
+        }
+
+        Ok((simulator, self.dt.unwrap_or(dt)))
+    }
+}
+
+fn number<T: FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{option}: {value:?} is not a valid number"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunOutcome {
+    Completed { steps: u32 },
+    /// the fields became non-finite or exceeded the speed limit after `step`
+    Diverged { step: u32, reason: String }
+}
+
+/// why the state can't be trusted anymore, if it can't
+pub fn check_health(simulator: &Simulator, max_speed: f64) -> Option<String> {
+    let grid = &simulator.grid;
+    let fields = [
+        ("velocity", grid.velocities_x.iter().chain(&grid.velocities_y).collect::<Vec<_>>()),
+        ("temperature", grid.temperature.iter().collect()),
+        ("dye", grid.dye.iter().collect()),
+        ("pressure", simulator.pressure.iter().collect())
+    ];
+    for (name, values) in fields {
+        if values.iter().any(|v| !v.is_finite()) {
+            return Some(format!("non-finite {name}"));
+        }
+    }
+
+    let speed = grid.velocities_x.iter().chain(&grid.velocities_y).fold(0.0, |m: f64, v| m.max(v.abs()));
+    (speed > max_speed).then(|| format!("face velocity {speed:.3e} exceeds {max_speed:e}"))
+}
+
+/// steps `simulator` as configured by `options`, printing progress to `log`
+pub fn run(simulator: &mut Simulator, dt: f64, options: &RunOptions, log: &mut impl Write) -> Result<RunOutcome, Box<dyn Error>> {
+    if let Some(projection) = options.projection {
+        simulator.projection = projection;
+    }
+    if let Some(path) = &options.checkpoint {
+        simulator.checkpoints.path = path.clone();
+        simulator.checkpoints.interval = options.checkpoint_every;
+    }
+
+    let mut vtk = options.vtk.as_ref().map(|directory| VtkSeries::new(directory, "flowy"));
+    // a resumed run continues the CSV of the run that wrote the checkpoint
+    let mut diagnostics = match &options.diagnostics {
+        Some(path) => Some(open_diagnostics(path, options.resume.is_some().then_some(simulator.time))?),
+        None => None
+    };
+    let mut recorder = options.record.as_ref().map(|path| {
+        let format = if path.extension().is_some_and(|e| e == "gif") { RecordingFormat::Gif } else { RecordingFormat::PngFrames };
+        Recorder::new(format, path, options.frame_skip)
+    });
+    let mut layers = LayerStack::new();
+    layers.temperature = Some(ColorScale::new(Colormap::Magma, Normalization::Auto));
+    layers.upsampling = 4;
+    layers.smooth = true;
+
+    let mut steps = 0;
+    let outcome = loop {
+        let done = options.steps.is_some_and(|n| steps >= n) || options.until.is_some_and(|t| simulator.time + 0.5 * dt > t);
+        if done {
+            break RunOutcome::Completed { steps };
+        }
+
+        simulator.step(dt);
+        steps += 1;
+
+        if let (Some(file), Some(d)) = (&mut diagnostics, simulator.diagnostics.samples().back()) {
+            file.write_all(d.csv_row().as_bytes())?;
+        }
+
+        if let Some(reason) = check_health(simulator, options.max_speed) {
+            break RunOutcome::Diverged { step: simulator.current_time_step, reason };
+        }
+        if let Some(Err(e)) = &simulator.checkpoints.last {
+            return Err(format!("checkpoint failed: {e}").into());
+        }
+
+        if !options.quiet && options.print_every > 0 && steps % options.print_every == 0 {
+            if let Some(d) = simulator.diagnostics.samples().back() {
+                writeln!(log, "step {:>6}  t = {:<10.4}  energy = {:.4e}  max div = {:.2e}  cfl = {:.3}  iterations = {}",
+                    simulator.current_time_step, d.time, d.kinetic_energy, d.max_divergence, d.cfl, d.solver_iterations)?;
+            }
+        }
+        if options.output_every > 0 && steps % options.output_every == 0 {
+            write_outputs(simulator, options, &mut vtk, &mut diagnostics)?;
+        }
+        if let Some(recorder) = &mut recorder {
+            if recorder.wants_frame() {
+                layers.points = simulator.particles.particles.iter().map(|p| (p.pos, epaint::Color32::from_rgb(255, 140, 0))).collect();
+                recorder.add_frame(&layers.render(simulator, options.frame_size))?;
+            }
+        }
+    };
+
+    // the final state, unless it was just written
+    if options.output_every == 0 || steps % options.output_every != 0 || matches!(outcome, RunOutcome::Diverged { .. }) {
+        write_outputs(simulator, options, &mut vtk, &mut diagnostics)?;
+    }
+    if let Some(recorder) = &mut recorder {
+        recorder.finish();
+    }
+
+    match &outcome {
+        RunOutcome::Completed { steps } => writeln!(log, "completed {steps} steps, t = {}", simulator.time)?,
+        RunOutcome::Diverged { step, reason } => writeln!(log, "diverged at step {step}: {reason}")?
+    }
+    Ok(outcome)
+}
+
+fn write_outputs(simulator: &Simulator, options: &RunOptions, vtk: &mut Option<VtkSeries>, diagnostics: &mut Option<BufWriter<File>>) -> Result<(), Box<dyn Error>> {
+    if let Some(series) = vtk {
+        series.write_step(&simulator.grid, &simulator.pressure, simulator.time, simulator.current_time_step)?;
+    }
+    if let Some(directory) = &options.npz {
+        fs::create_dir_all(directory)?;
+        arrays::export_npz(&simulator.grid, &directory.join(format!("fields_{:06}.npz", simulator.current_time_step)))?;
+    }
+    if let Some(file) = diagnostics {
+        file.flush()?;
+    }
+    Ok(())
+}
+
+/// the diagnostics CSV that every step appends a line to; when resuming at `resumed_at`, the lines
+/// up to that time are kept and those the interrupted run wrote after its checkpoint are dropped
+fn open_diagnostics(path: &Path, resumed_at: Option<f64>) -> Result<BufWriter<File>, Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut csv = DiagnosticsHistory::csv_header();
+    if let (Some(time), Ok(previous)) = (resumed_at, fs::read_to_string(path)) {
+        let rows = previous.lines().skip(1)
+            .take_while(|line| line.split(',').next().and_then(|t| t.parse::<f64>().ok()).is_some_and(|t| t <= time));
+        for row in rows {
+            csv.push_str(row);
+            csv.push('\n');
+        }
+    }
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(csv.as_bytes())?;
+    Ok(file)
+}
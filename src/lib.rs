@@ -0,0 +1,5 @@
+pub mod simulator;
+pub mod visualize;
+pub mod headless;
+#[cfg(test)]
+mod tests;
@@ -1,54 +1,34 @@
-use eframe::egui;
+use std::process::ExitCode;
 
-use simulator::grid::StaggeredMACGrid;
+use eframe::egui;
 
-use crate::{visualize::FlowyApp, simulator::{simulator::Simulator, math::vector2}};
+use flowy::{visualize::FlowyApp, simulator::{simulator::Simulator, scenario::Scenario}};
 
-mod simulator;
-mod visualize;
-mod tests;
+fn main() -> ExitCode {
+    // optional scenario file or built-in scenario name to start with instead of the demo setup
+    let mut app = FlowyApp::new(Simulator::demo());
+    if let Some(path) = std::env::args().nth(1) {
+        if let Err(e) = Scenario::open(&path).and_then(|scenario| app.load_scenario(&scenario)) {
+            eprintln!("flowy: {path}: {e}");
+            return ExitCode::from(1);
+        }
+    }
 
-fn main() -> Result<(), eframe::Error> {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_resizable(true),
         ..Default::default()
     };
 
-    eframe::run_native(
+    let result = eframe::run_native(
         "flowy",
         options,
-        Box::new(|cc| {
-            let mut grid = StaggeredMACGrid::new(20);
-            *grid.temp_grid_mut(2, 5) = 10.0;
-
-            let tcc = grid.velocities_x.len();
-
-            for (i, vx) in grid.velocities_x.iter_mut(). enumerate() {
-                *vx = i as f64 / tcc as f64;
-            }
-
-            for (i, vy) in grid.velocities_y.iter_mut().enumerate() {
-                *vy = i as f64 / tcc as f64;
-            }
-
-            for col in -1..=grid.cell_count {
-                *grid.vel_x_grid_mut(col as i32, -1) = 0.0;
-                *grid.vel_x_grid_mut(col as i32, grid.cell_count) = 0.0;
-                *grid.vel_y_grid_mut(-1, col as i32) = 0.0;
-                *grid.vel_y_grid_mut(grid.cell_count, col as i32) = 0.0;
-            }
-
-            for row in -1..=grid.cell_count {
-                *grid.vel_x_grid_mut(-1, row as i32) = 0.0;
-                *grid.vel_x_grid_mut(grid.cell_count + 1, row as i32) = 0.0;
-                *grid.vel_y_grid_mut(row as i32, -1) = 0.0;
-                *grid.vel_y_grid_mut(row as i32, grid.cell_count + 1) = 0.0;
-            }
-
-            let simulator = Simulator::new(grid);
-            let app = FlowyApp::new(simulator);
-
-            Box::new(app)
-        }),
-    )
+        Box::new(|_cc| Box::new(app)),
+    );
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("flowy: {e}");
+            ExitCode::from(1)
+        }
+    }
 }
@@ -0,0 +1,452 @@
+use std::{fmt::Write, fs, path::Path};
+
+use serde::{Serialize, Deserialize};
+
+use super::{grid::StaggeredMACGrid, io::FormatError, math::{vector2, Vector2}};
+
+/// a stored field of the grid as a 2D array indexed [y][x]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridField {
+    /// faces x = 0..=cc of rows y = 0..cc
+    VelocityX,
+    /// faces y = 0..=cc of columns x = 0..cc
+    VelocityY,
+    Temperature,
+    Dye
+}
+
+impl GridField {
+    pub const ALL: [GridField; 4] = [Self::VelocityX, Self::VelocityY, Self::Temperature, Self::Dye];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::VelocityX => "x velocity",
+            Self::VelocityY => "y velocity",
+            Self::Temperature => "temperature",
+            Self::Dye => "dye"
+        }
+    }
+
+    /// array name in `.npz` bundles
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::VelocityX => "u",
+            Self::VelocityY => "v",
+            Self::Temperature => "temperature",
+            Self::Dye => "dye"
+        }
+    }
+
+    /// (rows, columns) for `cell_count` cells per side
+    pub fn shape(&self, cell_count: i32) -> (usize, usize) {
+        let cc = cell_count as usize;
+        match self {
+            Self::VelocityX => (cc, cc + 1),
+            Self::VelocityY => (cc + 1, cc),
+            Self::Temperature | Self::Dye => (cc, cc)
+        }
+    }
+
+    /// values in row-major order
+    pub fn values(&self, grid: &StaggeredMACGrid) -> Vec<f64> {
+        let (rows, cols) = self.shape(grid.cell_count);
+        (0..rows as i32).flat_map(|y| (0..cols as i32).map(move |x| (x, y))).map(|(x, y)| match self {
+            Self::VelocityX => grid.vel_x_grid(x, y),
+            Self::VelocityY => grid.vel_y_grid(x, y),
+            Self::Temperature => grid.temp_grid(x, y),
+            Self::Dye => grid.dye_grid(x, y)
+        }).collect()
+    }
+
+    /// overwrites the field with row-major `values` after checking their shape
+    pub fn set(&self, grid: &mut StaggeredMACGrid, shape: (usize, usize), values: &[f64]) -> Result<(), FormatError> {
+        let (rows, cols) = self.shape(grid.cell_count);
+        if shape != (rows, cols) {
+            return Err(FormatError::Shape { expected: vec![rows, cols], found: vec![shape.0, shape.1] });
+        }
+
+        for (i, v) in values.iter().enumerate() {
+            *self.sample_mut(grid, i % cols, i / cols) = *v;
+        }
+        Ok(())
+    }
+
+    /// world position of sample [y][x]: face centers for the velocities, cell centers otherwise
+    pub fn position(&self, x: usize, y: usize) -> Vector2 {
+        match self {
+            Self::VelocityX => vector2(x as f64, y as f64 + 0.5),
+            Self::VelocityY => vector2(x as f64 + 0.5, y as f64),
+            Self::Temperature | Self::Dye => vector2(x as f64 + 0.5, y as f64 + 0.5)
+        }
+    }
+
+    /// replaces every sample with `f(position, value)`
+    pub fn map(&self, grid: &mut StaggeredMACGrid, f: impl Fn(Vector2, f64) -> f64) {
+        let (rows, cols) = self.shape(grid.cell_count);
+        for y in 0..rows {
+            for x in 0..cols {
+                let sample = self.sample_mut(grid, x, y);
+                *sample = f(self.position(x, y), *sample);
+            }
+        }
+    }
+
+    fn sample_mut<'a>(&self, grid: &'a mut StaggeredMACGrid, x: usize, y: usize) -> &'a mut f64 {
+        let (x, y) = (x as i32, y as i32);
+        match self {
+            Self::VelocityX => grid.vel_x_grid_mut(x, y),
+            Self::VelocityY => grid.vel_y_grid_mut(x, y),
+            Self::Temperature => grid.temp_grid_mut(x, y),
+            Self::Dye => grid.dye_grid_mut(x, y)
+        }
+    }
+
+    /// writes the field as `.npy` or, for any other extension, CSV
+    pub fn export(&self, grid: &StaggeredMACGrid, path: &Path) -> Result<(), FormatError> {
+        let shape = self.shape(grid.cell_count);
+        let values = self.values(grid);
+        if is_npy(path) {
+            fs::write(path, to_npy(shape, &values))?;
+        } else {
+            fs::write(path, to_csv(shape, &values))?;
+        }
+        Ok(())
+    }
+
+    /// reads `.npy` or CSV data written by `export` or elsewhere; the grid is unchanged if this fails
+    pub fn import(&self, grid: &mut StaggeredMACGrid, path: &Path) -> Result<(), FormatError> {
+        let (shape, values) = if is_npy(path) {
+            from_npy(&fs::read(path)?)?
+        } else {
+            from_csv(&fs::read_to_string(path)?)?
+        };
+
+        self.set(grid, shape, &values)?;
+        grid.enforce_obstacles();
+        Ok(())
+    }
+}
+
+fn is_npy(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "npy")
+}
+
+/// one line per row
+pub fn to_csv(shape: (usize, usize), values: &[f64]) -> String {
+    let mut csv = String::new();
+    for row in values.chunks(shape.1.max(1)) {
+        let line: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+        writeln!(csv, "{}", line.join(",")).unwrap();
+    }
+    csv
+}
+
+/// rows of comma separated numbers, blank lines and `#` comments (as written by `np.savetxt`) are skipped
+pub fn from_csv(text: &str) -> Result<((usize, usize), Vec<f64>), FormatError> {
+    let mut values = Vec::new();
+    let mut shape = (0, 0);
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let row = line.split(',')
+            .map(|v| v.trim().parse::<f64>().map_err(|_| FormatError::Invalid(format!("line {}: {:?} is not a number", i + 1, v.trim()))))
+            .collect::<Result<Vec<_>, _>>()?;
+        if shape.0 > 0 && row.len() != shape.1 {
+            return Err(FormatError::Invalid(format!("line {} has {} values, expected {}", i + 1, row.len(), shape.1)));
+        }
+
+        shape = (shape.0 + 1, row.len());
+        values.extend(row);
+    }
+
+    Ok((shape, values))
+}
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// NumPy format version 1.0, little-endian float64 in C order
+pub fn to_npy(shape: (usize, usize), values: &[f64]) -> Vec<u8> {
+    let mut header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, {}), }}", shape.0, shape.1);
+    // magic, version and header length take 10 bytes, the data starts 64-byte aligned
+    let padding = (64 - (10 + header.len() + 1) % 64) % 64;
+    header.extend(std::iter::repeat_n(' ', padding));
+    header.push('\n');
+
+    let mut npy = NPY_MAGIC.to_vec();
+    npy.extend_from_slice(&[1, 0]);
+    npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    npy.extend_from_slice(header.as_bytes());
+    for v in values {
+        npy.extend_from_slice(&v.to_le_bytes());
+    }
+    npy
+}
+
+/// value of `key` in the header dictionary, up to the end of the header
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, FormatError> {
+    let pattern = format!("'{key}':");
+    let start = header.find(&pattern).ok_or_else(|| FormatError::Invalid(format!("npy header without {key}")))?;
+    Ok(header[start + pattern.len()..].trim_start())
+}
+
+/// 2D arrays of floats or integers in either byte order, converted to float64 in C order
+pub fn from_npy(data: &[u8]) -> Result<((usize, usize), Vec<f64>), FormatError> {
+    if data.len() < 10 || &data[..6] != NPY_MAGIC {
+        return Err(FormatError::WrongKind { expected: "npy" });
+    }
+
+    let (header_len, header_start) = match data[6] {
+        1 => (u16::from_le_bytes([data[8], data[9]]) as usize, 10),
+        2 | 3 => {
+            let bytes = data.get(8..12).ok_or(FormatError::Truncated)?;
+            (u32::from_le_bytes(bytes.try_into().unwrap()) as usize, 12)
+        },
+        v => return Err(FormatError::UnsupportedVersion(v as u32))
+    };
+    let header_end = header_len.checked_add(header_start).ok_or(FormatError::Truncated)?;
+    let header = data.get(header_start..header_end).ok_or(FormatError::Truncated)?;
+    let header = std::str::from_utf8(header).map_err(|_| FormatError::Invalid(String::from("npy header is not text")))?;
+
+    let descr = header_value(header, "descr")?;
+    let descr = descr.get(1..).and_then(|d| d.split(['\'', '"']).next()).unwrap_or_default();
+    let fortran_order = header_value(header, "fortran_order")?.starts_with("True");
+    let shape = header_value(header, "shape")?;
+    let shape = match (shape.find('('), shape.find(')')) {
+        (Some(open), Some(close)) if open < close => &shape[open + 1..close],
+        _ => return Err(FormatError::Invalid(format!("npy shape {shape:?} is not a tuple")))
+    };
+    let shape: Vec<usize> = shape
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| d.parse().map_err(|_| FormatError::Invalid(format!("npy shape entry {d:?}"))))
+        .collect::<Result<_, _>>()?;
+    if shape.len() != 2 {
+        return Err(FormatError::Invalid(format!("expected a 2D array, found shape {shape:?}")));
+    }
+
+    let (little_endian, kind) = match descr.as_bytes().first() {
+        Some(b'<' | b'|' | b'=') => (true, &descr[1..]),
+        Some(b'>') => (false, &descr[1..]),
+        _ => (true, descr)
+    };
+    let size = match kind {
+        "f8" | "i8" => 8,
+        "f4" | "i4" => 4,
+        _ => return Err(FormatError::DType(descr.to_string()))
+    };
+
+    // a hostile shape can overflow the byte count, which can't match any body anyway
+    let length = shape[0].checked_mul(shape[1]).and_then(|count| count.checked_mul(size)).ok_or(FormatError::Truncated)?;
+    let body = &data[header_end..];
+    if body.len() < length {
+        return Err(FormatError::Truncated);
+    }
+    if body.len() > length {
+        return Err(FormatError::Invalid(format!("{} unexpected trailing bytes", body.len() - length)));
+    }
+
+    let values: Vec<f64> = body.chunks_exact(size).map(|b| {
+        let mut bytes = b.to_vec();
+        if !little_endian {
+            bytes.reverse();
+        }
+        match kind {
+            "f8" => f64::from_le_bytes(bytes.try_into().unwrap()),
+            "i8" => i64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            "f4" => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            _ => i32::from_le_bytes(bytes.try_into().unwrap()) as f64
+        }
+    }).collect();
+
+    let values = if fortran_order {
+        (0..values.len()).map(|i| values[(i % shape[1]) * shape[0] + i / shape[1]]).collect()
+    } else {
+        values
+    };
+
+    Ok(((shape[0], shape[1]), values))
+}
+
+/// all fields as an uncompressed `.npz` (like `np.savez`), keyed by `GridField::key`
+pub fn export_npz(grid: &StaggeredMACGrid, path: &Path) -> Result<(), FormatError> {
+    let files: Vec<(String, Vec<u8>)> = GridField::ALL.iter()
+        .map(|f| (format!("{}.npy", f.key()), to_npy(f.shape(grid.cell_count), &f.values(grid))))
+        .collect();
+    fs::write(path, zip_stored(&files))?;
+    Ok(())
+}
+
+/// sets every field found in the archive, checking all of them before the grid is changed;
+/// returns the imported fields
+pub fn import_npz(grid: &mut StaggeredMACGrid, path: &Path) -> Result<Vec<GridField>, FormatError> {
+    let files = unzip(&fs::read(path)?)?;
+
+    let mut updated = grid.clone();
+    let mut imported = Vec::new();
+    for field in GridField::ALL {
+        let Some((_, data)) = files.iter().find(|(name, _)| name.strip_suffix(".npy").unwrap_or(name) == field.key()) else {
+            continue;
+        };
+        let (shape, values) = from_npy(data)?;
+        field.set(&mut updated, shape, &values)?;
+        imported.push(field);
+    }
+
+    if imported.is_empty() {
+        let keys: Vec<&str> = GridField::ALL.iter().map(|f| f.key()).collect();
+        return Err(FormatError::Invalid(format!("the archive contains none of {}", keys.join(", "))));
+    }
+
+    updated.enforce_obstacles();
+    *grid = updated;
+    Ok(imported)
+}
+
+/// CRC-32 (IEEE) as used by zip archives
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// zip archive without compression
+fn zip_stored(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut zip = Vec::new();
+    let mut central = Vec::new();
+
+    for (name, data) in files {
+        let offset = zip.len() as u32;
+        // version needed, flags, method (stored), time, date (1980-01-01), crc, sizes
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0x21u16.to_le_bytes());
+        common.extend_from_slice(&crc32(data).to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        zip.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        zip.extend_from_slice(&common);
+        zip.extend_from_slice(name.as_bytes());
+        zip.extend_from_slice(data);
+
+        // version made by, then the local fields, comment length, disk, attributes and offset
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&common);
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = zip.len() as u32;
+    zip.extend_from_slice(&central);
+    zip.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    zip.extend_from_slice(&[0; 4]);
+    zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    zip.extend_from_slice(&central_offset.to_le_bytes());
+    zip.extend_from_slice(&0u16.to_le_bytes());
+    zip
+}
+
+fn le16(data: &[u8], pos: usize) -> Result<u16, FormatError> {
+    let bytes = pos.checked_add(2).and_then(|end| data.get(pos..end)).ok_or(FormatError::Truncated)?;
+    Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn le32(data: &[u8], pos: usize) -> Result<u32, FormatError> {
+    let bytes = pos.checked_add(4).and_then(|end| data.get(pos..end)).ok_or(FormatError::Truncated)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn le64(data: &[u8], pos: usize) -> Result<u64, FormatError> {
+    let bytes = pos.checked_add(8).and_then(|end| data.get(pos..end)).ok_or(FormatError::Truncated)?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// (name, contents) of every file in an uncompressed zip archive, located through the central
+/// directory since `np.savez` leaves the sizes in the local headers to a zip64 extra field
+fn unzip(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, FormatError> {
+    let end = (0..data.len().saturating_sub(21)).rev()
+        .find(|&i| data[i..i + 4] == 0x06054b50u32.to_le_bytes())
+        .ok_or(FormatError::WrongKind { expected: "npz" })?;
+    let count = le16(data, end + 10)? as usize;
+    let mut pos = le32(data, end + 16)? as usize;
+
+    let mut files = Vec::new();
+    for _ in 0..count {
+        if le32(data, pos)? != 0x02014b50 {
+            return Err(FormatError::Invalid(String::from("broken zip directory")));
+        }
+
+        let method = le16(data, pos + 10)?;
+        let crc = le32(data, pos + 16)?;
+        let mut size = le32(data, pos + 20)? as u64;
+        let name_len = le16(data, pos + 28)? as usize;
+        let extra_len = le16(data, pos + 30)? as usize;
+        let comment_len = le16(data, pos + 32)? as usize;
+        let mut offset = le32(data, pos + 42)? as u64;
+        let name = data.get(pos + 46..pos + 46 + name_len).ok_or(FormatError::Truncated)?;
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        // zip64 extra field: uncompressed size, compressed size and offset, each only if saturated above
+        let mut extra = pos + 46 + name_len;
+        while extra + 4 <= pos + 46 + name_len + extra_len {
+            let (id, len) = (le16(data, extra)?, le16(data, extra + 2)? as usize);
+            if id == 1 {
+                let mut field = extra + 4;
+                if le32(data, pos + 24)? == u32::MAX {
+                    field += 8;
+                }
+                if size == u32::MAX as u64 {
+                    size = le64(data, field)?;
+                    field += 8;
+                }
+                if offset == u32::MAX as u64 {
+                    offset = le64(data, field)?;
+                }
+            }
+            extra += 4 + len;
+        }
+
+        if method != 0 {
+            return Err(FormatError::Invalid(format!("{name} is compressed, only uncompressed archives (np.savez) are supported")));
+        }
+
+        let local = usize::try_from(offset).map_err(|_| FormatError::Truncated)?;
+        if le32(data, local)? != 0x04034b50 {
+            return Err(FormatError::Invalid(format!("broken zip entry {name}")));
+        }
+        let start = local + 30 + le16(data, local + 26)? as usize + le16(data, local + 28)? as usize;
+        // sizes come from the archive, so a hostile zip64 size must not overflow the range
+        let contents = usize::try_from(size).ok()
+            .and_then(|size| start.checked_add(size))
+            .and_then(|end| data.get(start..end))
+            .ok_or(FormatError::Truncated)?;
+        if crc32(contents) != crc {
+            return Err(FormatError::Invalid(format!("checksum mismatch in {name}")));
+        }
+
+        files.push((name, contents.to_vec()));
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+
+    Ok(files)
+}
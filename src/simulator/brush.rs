@@ -0,0 +1,97 @@
+use super::{grid::{StaggeredMACGrid, BoundaryPolicy}, math::{vector2, Vector2}};
+
+/// circular brush with a smooth falloff for editing the fields of a grid, positions and radius in world space;
+/// every application covers `dt` seconds of painting, so the result doesn't depend on the frame rate
+#[derive(Debug, Clone, PartialEq)]
+pub struct Brush {
+    pub radius: f64,
+    /// rate per second of everything the brush adds or removes
+    pub strength: f64
+}
+
+impl Brush {
+    pub fn new(radius: f64, strength: f64) -> Self {
+        Self { radius, strength }
+    }
+
+    /// 1 at the center falling smoothly to 0 at the radius
+    fn weight(&self, center: Vector2, pos: Vector2) -> f64 {
+        let r2 = (pos - center).len_squared() / (self.radius * self.radius);
+        if r2 < 1.0 { (1.0 - r2) * (1.0 - r2) } else { 0.0 }
+    }
+
+    /// index range along one axis covering the brush around `c` for samples at `index + offset`
+    fn span(&self, c: f64, offset: f64, min: i32, max: i32) -> std::ops::Range<i32> {
+        let lo = (c - self.radius - offset).floor() as i32;
+        let hi = (c + self.radius - offset).ceil() as i32;
+        lo.max(min)..(hi + 1).min(max)
+    }
+
+    /// cells (x, y) within the brush and their weights
+    fn cells(&self, grid: &StaggeredMACGrid, center: Vector2) -> Vec<(i32, i32, f64)> {
+        let cc = grid.cell_count;
+
+        self.span(center.y, 0.5, 0, cc)
+            .flat_map(|y| self.span(center.x, 0.5, 0, cc).map(move |x| (x, y)))
+            .map(|(x, y)| (x, y, self.weight(center, vector2(x as f64 + 0.5, y as f64 + 0.5))))
+            .filter(|(_, _, w)| *w > 0.0)
+            .collect()
+    }
+
+    /// adds `velocity` (e.g. that of the pointer) times `strength * dt` to the faces within the brush;
+    /// walls on the domain boundary are left alone
+    pub fn paint_velocity(&self, grid: &mut StaggeredMACGrid, center: Vector2, velocity: Vector2, dt: f64) {
+        let cc = grid.cell_count;
+        let first = if grid.boundary == BoundaryPolicy::Periodic { 0 } else { 1 };
+
+        for y in self.span(center.y, 0.5, 0, cc) {
+            for x in self.span(center.x, 0.0, first, cc) {
+                let w = self.weight(center, vector2(x as f64, y as f64 + 0.5));
+                *grid.vel_x_grid_mut(x, y) += self.strength * dt * w * velocity.x;
+            }
+        }
+        for x in self.span(center.x, 0.5, 0, cc) {
+            for y in self.span(center.y, 0.0, first, cc) {
+                let w = self.weight(center, vector2(x as f64 + 0.5, y as f64));
+                *grid.vel_y_grid_mut(x, y) += self.strength * dt * w * velocity.y;
+            }
+        }
+    }
+
+    pub fn paint_dye(&self, grid: &mut StaggeredMACGrid, center: Vector2, dt: f64) {
+        for (x, y, w) in self.cells(grid, center) {
+            *grid.dye_grid_mut(x, y) += self.strength * dt * w;
+        }
+    }
+
+    /// adds heat, or removes it for negative `amount`
+    pub fn paint_temperature(&self, grid: &mut StaggeredMACGrid, center: Vector2, amount: f64, dt: f64) {
+        for (x, y, w) in self.cells(grid, center) {
+            *grid.temp_grid_mut(x, y) += self.strength * dt * w * amount;
+        }
+    }
+
+    /// fades velocity, dye and temperature within the brush exponentially towards zero
+    pub fn erase(&self, grid: &mut StaggeredMACGrid, center: Vector2, dt: f64) {
+        let fade = |w: f64| (-self.strength * dt * w).exp();
+        let cc = grid.cell_count;
+
+        for (x, y, w) in self.cells(grid, center) {
+            *grid.dye_grid_mut(x, y) *= fade(w);
+            *grid.temp_grid_mut(x, y) *= fade(w);
+        }
+
+        for y in self.span(center.y, 0.5, 0, cc) {
+            for x in self.span(center.x, 0.0, 0, cc + 1) {
+                let w = self.weight(center, vector2(x as f64, y as f64 + 0.5));
+                *grid.vel_x_grid_mut(x, y) *= fade(w);
+            }
+        }
+        for x in self.span(center.x, 0.5, 0, cc) {
+            for y in self.span(center.y, 0.0, 0, cc + 1) {
+                let w = self.weight(center, vector2(x as f64 + 0.5, y as f64));
+                *grid.vel_y_grid_mut(x, y) *= fade(w);
+            }
+        }
+    }
+}
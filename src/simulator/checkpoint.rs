@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use super::{simulator::Simulator, grid::StaggeredMACGrid, particles::Particles, pressure::{PressureSolver, ProjectionStats}, diagnostics::DiagnosticsHistory, probes::Probes, forces::Forces, io::{Writer, Reader, FormatError}};
+
+const MAGIC: &[u8; 8] = b"FLOWYCKP";
+/// version 2 added the forces, version 3 the time step
+const VERSION: u32 = 3;
+
+/// when and where `Simulator::step` writes checkpoints
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckpointSchedule {
+    /// steps between checkpoints, 0 disables them
+    pub interval: u32,
+    /// overwritten by every checkpoint
+    pub path: PathBuf,
+    /// step count of the last checkpoint written, or why writing it failed
+    pub last: Option<Result<u32, String>>
+}
+
+impl Default for CheckpointSchedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CheckpointSchedule {
+    pub fn new() -> Self {
+        Self { interval: 0, path: PathBuf::from("checkpoint.flowy"), last: None }
+    }
+
+    pub fn is_due(&self, time_step: u32) -> bool {
+        self.interval > 0 && time_step.is_multiple_of(self.interval)
+    }
+}
+
+impl Simulator {
+    /// writes the whole simulation state (fields, particles and their random number generator,
+    /// solver settings and warm start, forces, the bounded histories) and the time step `dt` of the run
+    /// so a restored run continues bit for bit; longer records are streamed to files instead
+    pub fn save_checkpoint(&self, path: &Path, dt: f64) -> Result<(), FormatError> {
+        let mut w = Writer::new(MAGIC, VERSION);
+
+        w.u32(self.current_time_step);
+        w.f64(self.time);
+        w.f64(dt);
+        w.bool(self.projection);
+        w.length(self.pressure_solver.max_iterations);
+        w.f64(self.pressure_solver.tolerance);
+        w.f64s(&self.pressure);
+        w.length(self.projection_stats.iterations);
+        w.f64(self.projection_stats.residual);
+
+        self.grid.write(&mut w);
+        self.particles.write(&mut w);
+        self.diagnostics.write(&mut w);
+        self.probes.write(&mut w);
+        self.forces.write(&mut w);
+
+        Ok(w.save(path)?)
+    }
+
+    /// replaces the state with a checkpoint and returns the time step of the run, which checkpoints
+    /// before version 3 don't know; the schedule is kept and nothing changes if reading fails
+    pub fn restore_checkpoint(&mut self, path: &Path) -> Result<Option<f64>, FormatError> {
+        let data = std::fs::read(path)?;
+        let (mut r, version) = Reader::new(&data, MAGIC, "checkpoint")?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+
+        let current_time_step = r.u32()?;
+        let time = r.f64()?;
+        let dt = if version >= 3 { Some(r.f64()?) } else { None };
+        if dt.is_some_and(|dt| !(dt > 0.0 && dt.is_finite())) {
+            return Err(FormatError::Invalid(format!("time step {} must be positive", dt.unwrap())));
+        }
+        let projection = r.bool()?;
+        let pressure_solver = PressureSolver { max_iterations: r.u64()? as usize, tolerance: r.f64()? };
+        pressure_solver.validate().map_err(FormatError::Invalid)?;
+        let pressure = r.f64s()?;
+        let projection_stats = ProjectionStats { iterations: r.u64()? as usize, residual: r.f64()? };
+
+        let grid = StaggeredMACGrid::read(&mut r)?;
+        if !pressure.is_empty() && pressure.len() != (grid.cell_count * grid.cell_count) as usize {
+            return Err(FormatError::Invalid(format!("{} pressures for {} cells", pressure.len(), grid.cell_count)));
+        }
+        let particles = Particles::read(&mut r)?;
+        let diagnostics = DiagnosticsHistory::read(&mut r)?;
+        let probes = Probes::read(&mut r)?;
+        let forces = if version >= 2 { Forces::read(&mut r)? } else { Forces::new() };
+        r.finish()?;
+
+        *self = Self {
+            grid,
+            particles,
+            forces,
+            projection,
+            pressure_solver,
+            pressure,
+            projection_stats,
+            diagnostics,
+            probes,
+            checkpoints: self.checkpoints.clone(),
+            current_time_step,
+            time,
+            last_stepped: Local::now().time()
+        };
+        Ok(dt)
+    }
+}
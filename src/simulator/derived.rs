@@ -0,0 +1,66 @@
+use super::{grid::StaggeredMACGrid, pressure::PressureSolver};
+
+/// scalar quantities derived from the velocity field, evaluated at the cell centers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DerivedField {
+    Speed,
+    /// z component of the curl
+    Vorticity,
+    Divergence,
+    /// pressure of the last projection
+    Pressure,
+    /// second invariant of the velocity gradient, positive where rotation dominates strain
+    QCriterion
+}
+
+impl DerivedField {
+    pub const ALL: [DerivedField; 5] = [Self::Speed, Self::Vorticity, Self::Divergence, Self::Pressure, Self::QCriterion];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Speed => "speed",
+            Self::Vorticity => "vorticity",
+            Self::Divergence => "divergence",
+            Self::Pressure => "pressure",
+            Self::QCriterion => "Q-criterion"
+        }
+    }
+
+    /// values of all interior cells, row by row; `pressure` is only used for the pressure field
+    pub fn compute(&self, grid: &StaggeredMACGrid, pressure: &[f64]) -> Vec<f64> {
+        let cc = grid.cell_count;
+        (0..cc).flat_map(|y| (0..cc).map(move |x| (x, y))).map(|(x, y)| self.at(grid, pressure, x, y)).collect()
+    }
+
+    /// value at interior cell (x, y)
+    pub fn at(&self, grid: &StaggeredMACGrid, pressure: &[f64], x: i32, y: i32) -> f64 {
+        let cc = grid.cell_count;
+
+        match self {
+            Self::Speed => grid.vel_center(x, y).len(),
+            Self::Divergence => PressureSolver::divergence_at(grid, x, y),
+            Self::Pressure => pressure.get((x + y * cc) as usize).copied().filter(|_| pressure.len() == (cc * cc) as usize).unwrap_or(0.0),
+            Self::Vorticity => {
+                let [_, du_dy, dv_dx, _] = velocity_gradient(grid, x, y);
+                dv_dx - du_dy
+            },
+            Self::QCriterion => {
+                // Q = (|Ω|² - |S|²) / 2 with the rate of rotation Ω and strain S, expanded for 2D
+                let [du_dx, du_dy, dv_dx, dv_dy] = velocity_gradient(grid, x, y);
+                -0.5 * (du_dx * du_dx + dv_dy * dv_dy) - du_dy * dv_dx
+            }
+        }
+    }
+}
+
+/// [du/dx, du/dy, dv/dx, dv/dy] at the center of cell (x, y): normal derivatives from the enclosing
+/// faces, cross derivatives as central differences of the neighbouring cell-centered velocities
+/// (the ghost cells provide the neighbours at the boundary)
+fn velocity_gradient(grid: &StaggeredMACGrid, x: i32, y: i32) -> [f64; 4] {
+    let du_dx = grid.vel_x_grid(x + 1, y) - grid.vel_x_grid(x, y);
+    let dv_dy = grid.vel_y_grid(x, y + 1) - grid.vel_y_grid(x, y);
+    let du_dy = (grid.vel_center(x, y + 1).x - grid.vel_center(x, y - 1).x) / 2.0;
+    let dv_dx = (grid.vel_center(x + 1, y).y - grid.vel_center(x - 1, y).y) / 2.0;
+
+    [du_dx, du_dy, dv_dx, dv_dy]
+}
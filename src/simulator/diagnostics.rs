@@ -0,0 +1,196 @@
+use std::{collections::VecDeque, fmt::Write};
+
+use super::{simulator::Simulator, derived::DerivedField, pressure::PressureSolver, io::{Writer, Reader, FormatError}};
+
+/// global measures of the simulation state after one step
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Diagnostics {
+    /// simulation time after the step
+    pub time: f64,
+    pub kinetic_energy: f64,
+    pub enstrophy: f64,
+    pub max_divergence: f64,
+    pub temperature_mass: f64,
+    pub dye_mass: f64,
+    /// largest face velocity times the time step (in cells)
+    pub cfl: f64,
+    pub solver_iterations: usize,
+    /// wall-clock duration of the step in milliseconds
+    pub step_time: f64
+}
+
+impl Diagnostics {
+    /// measures the fluid cells of `simulator` after a step of `dt` that took `step_time` milliseconds
+    pub fn measure(simulator: &Simulator, dt: f64, step_time: f64) -> Self {
+        let grid = &simulator.grid;
+        let cc = grid.cell_count;
+        let fluid_cells = || (0..cc).flat_map(|y| (0..cc).map(move |x| (x, y))).filter(|(x, y)| !grid.is_solid(*x, *y));
+
+        let max_face_velocity = grid.velocities_x.iter().chain(&grid.velocities_y).fold(0.0, |m: f64, v| m.max(v.abs()));
+
+        Self {
+            time: simulator.time,
+            kinetic_energy: fluid_cells().map(|(x, y)| 0.5 * grid.vel_center(x, y).len_squared()).sum(),
+            enstrophy: fluid_cells().map(|(x, y)| 0.5 * DerivedField::Vorticity.at(grid, &[], x, y).powi(2)).sum(),
+            max_divergence: fluid_cells().map(|(x, y)| PressureSolver::divergence_at(grid, x, y).abs()).fold(0.0, f64::max),
+            temperature_mass: fluid_cells().map(|(x, y)| grid.temp_grid(x, y)).sum(),
+            dye_mass: fluid_cells().map(|(x, y)| grid.dye_grid(x, y)).sum(),
+            cfl: max_face_velocity * dt,
+            solver_iterations: if simulator.projection { simulator.projection_stats.iterations } else { 0 },
+            step_time
+        }
+    }
+
+    /// one line of `DiagnosticsHistory::to_csv`
+    pub fn csv_row(&self) -> String {
+        let mut row = self.time.to_string();
+        for series in DiagnosticSeries::ALL {
+            write!(row, ",{}", series.value(self)).unwrap();
+        }
+        row.push('\n');
+        row
+    }
+}
+
+/// one plottable column of the diagnostics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeries {
+    KineticEnergy,
+    Enstrophy,
+    MaxDivergence,
+    TemperatureMass,
+    DyeMass,
+    Cfl,
+    SolverIterations,
+    StepTime
+}
+
+impl DiagnosticSeries {
+    pub const ALL: [DiagnosticSeries; 8] = [
+        Self::KineticEnergy, Self::Enstrophy, Self::MaxDivergence, Self::TemperatureMass,
+        Self::DyeMass, Self::Cfl, Self::SolverIterations, Self::StepTime
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::KineticEnergy => "kinetic energy",
+            Self::Enstrophy => "enstrophy",
+            Self::MaxDivergence => "max. divergence",
+            Self::TemperatureMass => "total temperature",
+            Self::DyeMass => "total dye",
+            Self::Cfl => "CFL number",
+            Self::SolverIterations => "solver iterations",
+            Self::StepTime => "step time (ms)"
+        }
+    }
+
+    /// column header in CSV exports
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::KineticEnergy => "kinetic_energy",
+            Self::Enstrophy => "enstrophy",
+            Self::MaxDivergence => "max_divergence",
+            Self::TemperatureMass => "temperature_mass",
+            Self::DyeMass => "dye_mass",
+            Self::Cfl => "cfl",
+            Self::SolverIterations => "solver_iterations",
+            Self::StepTime => "step_time_ms"
+        }
+    }
+
+    pub fn value(&self, diagnostics: &Diagnostics) -> f64 {
+        match self {
+            Self::KineticEnergy => diagnostics.kinetic_energy,
+            Self::Enstrophy => diagnostics.enstrophy,
+            Self::MaxDivergence => diagnostics.max_divergence,
+            Self::TemperatureMass => diagnostics.temperature_mass,
+            Self::DyeMass => diagnostics.dye_mass,
+            Self::Cfl => diagnostics.cfl,
+            Self::SolverIterations => diagnostics.solver_iterations as f64,
+            Self::StepTime => diagnostics.step_time
+        }
+    }
+}
+
+/// the diagnostics of the most recent steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticsHistory {
+    /// number of steps kept
+    pub capacity: usize,
+    samples: VecDeque<Diagnostics>
+}
+
+impl DiagnosticsHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, samples: VecDeque::new() }
+    }
+
+    pub fn push(&mut self, diagnostics: Diagnostics) {
+        self.samples.push_back(diagnostics);
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn samples(&self) -> &VecDeque<Diagnostics> {
+        &self.samples
+    }
+
+    /// (time, value) pairs of one series, oldest first
+    pub fn series(&self, series: DiagnosticSeries) -> Vec<(f64, f64)> {
+        self.samples.iter().map(|d| (d.time, series.value(d))).collect()
+    }
+
+    /// all series as comma separated values with a header line
+    pub fn to_csv(&self) -> String {
+        let mut csv = Self::csv_header();
+        for sample in &self.samples {
+            csv.push_str(&sample.csv_row());
+        }
+        csv
+    }
+
+    /// header line of `to_csv`, for writing the rows of a long run one at a time
+    pub fn csv_header() -> String {
+        let mut header = String::from("time");
+        for series in DiagnosticSeries::ALL {
+            write!(header, ",{}", series.key()).unwrap();
+        }
+        header.push('\n');
+        header
+    }
+
+    pub fn write(&self, w: &mut Writer) {
+        w.length(self.capacity);
+        w.length(self.samples.len());
+        for d in &self.samples {
+            for v in [d.time, d.kinetic_energy, d.enstrophy, d.max_divergence, d.temperature_mass, d.dye_mass, d.cfl] {
+                w.f64(v);
+            }
+            w.length(d.solver_iterations);
+            w.f64(d.step_time);
+        }
+    }
+
+    pub fn read(r: &mut Reader) -> Result<Self, FormatError> {
+        let mut history = Self::new(r.u64()? as usize);
+        for _ in 0..r.length()? {
+            history.samples.push_back(Diagnostics {
+                time: r.f64()?,
+                kinetic_energy: r.f64()?,
+                enstrophy: r.f64()?,
+                max_divergence: r.f64()?,
+                temperature_mass: r.f64()?,
+                dye_mass: r.f64()?,
+                cfl: r.f64()?,
+                solver_iterations: r.u64()? as usize,
+                step_time: r.f64()?
+            });
+        }
+        Ok(history)
+    }
+}
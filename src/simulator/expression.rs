@@ -0,0 +1,367 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::{grid::StaggeredMACGrid, arrays::GridField};
+
+/// why an expression could not be parsed; `position` is a character offset into the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionError {
+    pub position: usize,
+    pub message: String
+}
+
+impl Display for ExpressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at character {}", self.message, self.position + 1)
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    /// position across the domain, 0 at the left wall and 1 at the right one
+    X,
+    /// position down the domain, 0 at the top wall and 1 at the bottom one
+    Y,
+    /// cells per side, e.g. for positions in cells as `x * n`
+    N
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Sin, Cos, Tan, Asin, Acos, Atan, Atan2, Sinh, Cosh, Tanh,
+    Exp, Ln, Log10, Sqrt, Abs, Sign, Floor, Ceil, Min, Max, Pow,
+    /// 0 below the edge, 1 from it on: `step(edge, value)`
+    Step
+}
+
+impl Function {
+    pub const ALL: [Function; 22] = [
+        Self::Sin, Self::Cos, Self::Tan, Self::Asin, Self::Acos, Self::Atan, Self::Atan2, Self::Sinh, Self::Cosh, Self::Tanh,
+        Self::Exp, Self::Ln, Self::Log10, Self::Sqrt, Self::Abs, Self::Sign, Self::Floor, Self::Ceil, Self::Min, Self::Max,
+        Self::Pow, Self::Step
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sin => "sin",
+            Self::Cos => "cos",
+            Self::Tan => "tan",
+            Self::Asin => "asin",
+            Self::Acos => "acos",
+            Self::Atan => "atan",
+            Self::Atan2 => "atan2",
+            Self::Sinh => "sinh",
+            Self::Cosh => "cosh",
+            Self::Tanh => "tanh",
+            Self::Exp => "exp",
+            Self::Ln => "ln",
+            Self::Log10 => "log10",
+            Self::Sqrt => "sqrt",
+            Self::Abs => "abs",
+            Self::Sign => "sign",
+            Self::Floor => "floor",
+            Self::Ceil => "ceil",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Pow => "pow",
+            Self::Step => "step"
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self {
+            Self::Atan2 | Self::Min | Self::Max | Self::Pow | Self::Step => 2,
+            _ => 1
+        }
+    }
+
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            Self::Sin => a.sin(),
+            Self::Cos => a.cos(),
+            Self::Tan => a.tan(),
+            Self::Asin => a.asin(),
+            Self::Acos => a.acos(),
+            Self::Atan => a.atan(),
+            Self::Atan2 => a.atan2(b),
+            Self::Sinh => a.sinh(),
+            Self::Cosh => a.cosh(),
+            Self::Tanh => a.tanh(),
+            Self::Exp => a.exp(),
+            Self::Ln => a.ln(),
+            Self::Log10 => a.log10(),
+            Self::Sqrt => a.sqrt(),
+            Self::Abs => a.abs(),
+            Self::Sign => if a == 0.0 { 0.0 } else { a.signum() },
+            Self::Floor => a.floor(),
+            Self::Ceil => a.ceil(),
+            Self::Min => a.min(b),
+            Self::Max => a.max(b),
+            Self::Pow => a.powf(b),
+            Self::Step => if b < a { 0.0 } else { 1.0 }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow
+}
+
+/// syntax tree of an arithmetic expression in x and y, which go from 0 to 1 across the domain
+/// unlike the cell units of world space elsewhere; `n` converts between both
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    Variable(Variable),
+    Negate(Box<Expression>),
+    Binary(BinaryOp, Box<Expression>, Box<Expression>),
+    Call(Function, Vec<Expression>)
+}
+
+impl Expression {
+    /// e.g. `sin(pi*x) * cos(pi*y)`; supports + - * / ^ (or **), parentheses, the constants pi and e,
+    /// the variables x, y and n and the functions in `Function::ALL`
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        let mut parser = Parser { chars: source.chars().collect(), pos: 0, depth: 0 };
+        let expression = parser.sum()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error(format!("unexpected '{}'", parser.chars[parser.pos])));
+        }
+        Ok(expression)
+    }
+
+    /// value at domain position (x, y) in [0, 1]² of a grid with `n` cells per side
+    pub fn eval(&self, x: f64, y: f64, n: f64) -> f64 {
+        match self {
+            Self::Number(v) => *v,
+            Self::Variable(Variable::X) => x,
+            Self::Variable(Variable::Y) => y,
+            Self::Variable(Variable::N) => n,
+            Self::Negate(a) => -a.eval(x, y, n),
+            Self::Binary(op, a, b) => {
+                let (a, b) = (a.eval(x, y, n), b.eval(x, y, n));
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Pow => a.powf(b)
+                }
+            },
+            Self::Call(function, args) => {
+                let a = args[0].eval(x, y, n);
+                let b = args.get(1).map_or(0.0, |b| b.eval(x, y, n));
+                function.apply(a, b)
+            }
+        }
+    }
+
+    /// sets `field` to the expression evaluated at its face or cell center positions;
+    /// the grid is unchanged if any value isn't finite
+    pub fn fill(&self, grid: &mut StaggeredMACGrid, field: GridField) -> Result<(), String> {
+        let n = grid.cell_count as f64;
+        let (rows, cols) = field.shape(grid.cell_count);
+        let mut values = Vec::with_capacity(rows * cols);
+        for y in 0..rows {
+            for x in 0..cols {
+                let p = field.position(x, y);
+                let value = self.eval(p.x / n, p.y / n, n);
+                if !value.is_finite() {
+                    return Err(format!("{} is {value} at x = {}, y = {}", field.name(), p.x / n, p.y / n));
+                }
+                values.push(value);
+            }
+        }
+
+        field.set(grid, (rows, cols), &values).expect("values have the shape of the field");
+        Ok(())
+    }
+}
+
+impl FromStr for Expression {
+    type Err = ExpressionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// deepest syntax tree `Expression::parse` builds, so neither parsing nor evaluating it overflows the stack
+const MAX_DEPTH: usize = 256;
+
+/// recursive descent, one method per precedence level
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// nesting of the syntax tree built so far
+    depth: usize
+}
+
+impl Parser {
+    fn error(&self, message: String) -> ExpressionError {
+        ExpressionError { position: self.pos, message }
+    }
+
+    /// goes one level deeper into the syntax tree, the caller goes back up by decrementing `depth`
+    fn nest(&mut self) -> Result<(), ExpressionError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(format!("expression nested deeper than {MAX_DEPTH} levels")));
+        }
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// skips whitespace and consumes `token` if it comes next
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let matches = token.chars().enumerate().all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+        if matches {
+            self.pos += token.len();
+        }
+        matches
+    }
+
+    // sum = product (('+' | '-') product)*
+    // every operator nests the terms before it one level deeper
+    fn sum(&mut self) -> Result<Expression, ExpressionError> {
+        let depth = self.depth;
+        let mut left = self.product()?;
+        loop {
+            let op = if self.eat("+") { BinaryOp::Add } else if self.eat("-") { BinaryOp::Sub } else { break };
+            self.nest()?;
+            left = Expression::Binary(op, Box::new(left), Box::new(self.product()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    // product = unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<Expression, ExpressionError> {
+        let depth = self.depth;
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat("*") { BinaryOp::Mul } else if self.eat("/") { BinaryOp::Div } else { break };
+            self.nest()?;
+            left = Expression::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        self.depth = depth;
+        Ok(left)
+    }
+
+    // unary = ('-' | '+') unary | power, so -x^2 is -(x^2)
+    fn unary(&mut self) -> Result<Expression, ExpressionError> {
+        let negate = if self.eat("-") {
+            true
+        } else if self.eat("+") {
+            false
+        } else {
+            return self.power();
+        };
+
+        self.nest()?;
+        let operand = self.unary()?;
+        self.depth -= 1;
+        Ok(if negate { Expression::Negate(Box::new(operand)) } else { operand })
+    }
+
+    // power = primary (('^' | '**') unary)?, right associative
+    fn power(&mut self) -> Result<Expression, ExpressionError> {
+        let base = self.primary()?;
+        if self.eat("^") || self.eat("**") {
+            self.nest()?;
+            let exponent = self.unary()?;
+            self.depth -= 1;
+            Ok(Expression::Binary(BinaryOp::Pow, Box::new(base), Box::new(exponent)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    // primary = number | name | name '(' sum (',' sum)* ')' | '(' sum ')'
+    fn primary(&mut self) -> Result<Expression, ExpressionError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let Some(&c) = self.chars.get(self.pos) else {
+            return Err(self.error(String::from("unexpected end of expression")));
+        };
+
+        if c == '(' {
+            self.pos += 1;
+            self.nest()?;
+            let inner = self.sum()?;
+            if !self.eat(")") {
+                return Err(self.error(String::from("expected ')'")));
+            }
+            self.depth -= 1;
+            return Ok(inner);
+        }
+
+        if c.is_ascii_digit() || c == '.' {
+            while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit() || *c == '.') {
+                self.pos += 1;
+            }
+            // exponent, unless the 'e' starts something else
+            if matches!(self.chars.get(self.pos), Some('e' | 'E')) {
+                let digits = if matches!(self.chars.get(self.pos + 1), Some('+' | '-')) { self.pos + 2 } else { self.pos + 1 };
+                if self.chars.get(digits).is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos = digits;
+                    while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit()) {
+                        self.pos += 1;
+                    }
+                }
+            }
+            let text: String = self.chars[start..self.pos].iter().collect();
+            return text.parse().map(Expression::Number).map_err(|_| ExpressionError { position: start, message: format!("invalid number {text}") });
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            while self.chars.get(self.pos).is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+                self.pos += 1;
+            }
+            let name: String = self.chars[start..self.pos].iter().collect();
+
+            if let Some(function) = Function::ALL.into_iter().find(|f| f.name() == name) {
+                if !self.eat("(") {
+                    return Err(self.error(format!("expected '(' after {name}")));
+                }
+                self.nest()?;
+                let mut args = vec![self.sum()?];
+                while self.eat(",") {
+                    args.push(self.sum()?);
+                }
+                if !self.eat(")") {
+                    return Err(self.error(String::from("expected ')'")));
+                }
+                self.depth -= 1;
+                if args.len() != function.arity() {
+                    return Err(ExpressionError { position: start, message: format!("{name} takes {} argument(s), not {}", function.arity(), args.len()) });
+                }
+                return Ok(Expression::Call(function, args));
+            }
+
+            return match name.as_str() {
+                "x" => Ok(Expression::Variable(Variable::X)),
+                "y" => Ok(Expression::Variable(Variable::Y)),
+                "n" => Ok(Expression::Variable(Variable::N)),
+                "pi" => Ok(Expression::Number(std::f64::consts::PI)),
+                "e" => Ok(Expression::Number(std::f64::consts::E)),
+                _ => Err(ExpressionError { position: start, message: format!("unknown name {name}") })
+            };
+        }
+
+        Err(self.error(format!("unexpected '{c}'")))
+    }
+}
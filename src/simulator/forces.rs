@@ -0,0 +1,97 @@
+use serde::{Serialize, Deserialize};
+
+use super::{grid::{StaggeredMACGrid, BoundaryPolicy}, arrays::GridField, obstacles::Obstacle, math::{vector2, Vector2}, io::{Writer, Reader, FormatError, index_of}};
+
+/// a field set to `value` wherever its samples lie inside `region`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Patch {
+    pub field: GridField,
+    pub value: f64,
+    pub region: Obstacle
+}
+
+impl Patch {
+    pub fn apply(&self, grid: &mut StaggeredMACGrid) {
+        self.field.map(grid, |p, v| if self.region.contains(p) { self.value } else { v });
+    }
+
+    pub fn write(&self, w: &mut Writer) {
+        w.u8(index_of(&GridField::ALL, &self.field));
+        w.f64(self.value);
+        self.region.write(w);
+    }
+
+    pub fn read(r: &mut Reader) -> Result<Self, FormatError> {
+        Ok(Self { field: r.choice(&GridField::ALL, "field")?, value: r.f64()?, region: Obstacle::read(r)? })
+    }
+}
+
+/// body forces and sources applied at the start of every step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Forces {
+    /// uniform acceleration, y points down
+    pub gravity: Vector2,
+    /// upward acceleration per unit of temperature above `ambient_temperature`
+    pub buoyancy: f64,
+    pub ambient_temperature: f64,
+    /// regions held at fixed values, e.g. inflows, moving lids and heat sources
+    pub sources: Vec<Patch>
+}
+
+impl Default for Forces {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Forces {
+    /// no forces at all
+    pub fn new() -> Self {
+        Self { gravity: vector2(0.0, 0.0), buoyancy: 0.0, ambient_temperature: 0.0, sources: Vec::new() }
+    }
+
+    /// accelerates the interior faces by `dt`, then imposes the sources
+    pub fn apply(&self, grid: &mut StaggeredMACGrid, dt: f64) {
+        let cc = grid.cell_count;
+        // walls keep their normal velocities, in periodic domains face cc is the same as face 0
+        let faces = if grid.boundary == BoundaryPolicy::Periodic { 0..cc } else { 1..cc };
+
+        if self.gravity.x != 0.0 {
+            for y in 0..cc {
+                for x in faces.clone() {
+                    *grid.vel_x_grid_mut(x, y) += dt * self.gravity.x;
+                }
+            }
+        }
+        if self.gravity.y != 0.0 || self.buoyancy != 0.0 {
+            for y in faces.clone() {
+                for x in 0..cc {
+                    let temperature = 0.5 * (grid.temp_grid(x, (y + cc - 1) % cc) + grid.temp_grid(x, y));
+                    *grid.vel_y_grid_mut(x, y) += dt * (self.gravity.y - self.buoyancy * (temperature - self.ambient_temperature));
+                }
+            }
+        }
+
+        for source in &self.sources {
+            source.apply(grid);
+        }
+        grid.enforce_obstacles();
+    }
+
+    pub fn write(&self, w: &mut Writer) {
+        w.vector2(self.gravity);
+        w.f64(self.buoyancy);
+        w.f64(self.ambient_temperature);
+        w.length(self.sources.len());
+        self.sources.iter().for_each(|s| s.write(w));
+    }
+
+    pub fn read(r: &mut Reader) -> Result<Self, FormatError> {
+        let (gravity, buoyancy, ambient_temperature) = (r.vector2()?, r.f64()?, r.f64()?);
+        let sources = r.length()?;
+        let sources = (0..sources).map(|_| Patch::read(r)).collect::<Result<_, _>>()?;
+        Ok(Self { gravity, buoyancy, ambient_temperature, sources })
+    }
+}
@@ -1,6 +1,32 @@
 use std::fmt::Display;
 
-use super::{math::{vector2, Vector2}, interpolation::{Interpolation, CubicInterpolation, LinearInterpolation}};
+use serde::{Serialize, Deserialize};
+
+use super::{math::{vector2, Vector2}, interpolation::{Interpolation2DKind, MAX_WIDTH}, obstacles::Obstacle, io::{Writer, Reader, FormatError, index_of}};
+
+/// how fields are continued outside the stored lattice (interior and ghost cells)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoundaryPolicy {
+    /// repeat the outermost stored value
+    Clamp,
+    /// everything outside the stored lattice is zero
+    Zero,
+    /// the interior cells repeat in both directions, ghost cells are ignored
+    Periodic
+}
+
+impl BoundaryPolicy {
+    pub const ALL: [BoundaryPolicy; 3] = [Self::Clamp, Self::Zero, Self::Periodic];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Clamp => "clamp",
+            Self::Zero => "zero",
+            Self::Periodic => "periodic"
+        }
+    }
+}
 
 #[derive(Clone, PartialEq)]
 pub struct StaggeredMACGrid {
@@ -10,6 +36,18 @@ pub struct StaggeredMACGrid {
 
     // for now only a mock quantity for advection tests
     pub temperature: Vec<f64>,
+    // passive dye concentration, stored like the temperature
+    pub dye: Vec<f64>,
+
+    // interpolation kernel used when sampling each field (the scalar kernel for temperature and dye)
+    pub vel_interpolation: Interpolation2DKind,
+    pub temp_interpolation: Interpolation2DKind,
+
+    pub boundary: BoundaryPolicy,
+
+    // solid obstacles and the cells whose centers they cover (row by row), see `update_obstacles`
+    pub obstacles: Vec<Obstacle>,
+    solid: Vec<bool>
 }
 
 impl StaggeredMACGrid {
@@ -20,7 +58,17 @@ impl StaggeredMACGrid {
             cell_count,
             velocities_x: vec![0.0; (cc2 * (cc2 + 1)) as usize],
             velocities_y: vec![0.0; (cc2 * (cc2 + 1)) as usize],
-            temperature: vec![0.0; (cc2 * cc2) as usize]
+            temperature: vec![0.0; (cc2 * cc2) as usize],
+            dye: vec![0.0; (cc2 * cc2) as usize],
+
+            vel_interpolation: Interpolation2DKind::Bicubic,
+            // overshooting kernels can make temperature negative
+            temp_interpolation: Interpolation2DKind::MonotoneBicubic,
+
+            boundary: BoundaryPolicy::Clamp,
+
+            obstacles: Vec::new(),
+            solid: vec![false; (cell_count * cell_count) as usize]
         }
     }
 
@@ -49,20 +97,129 @@ impl StaggeredMACGrid {
         &mut self.temperature[((x + 1) + (y + 1) * (self.cell_count + 2)) as usize]
     }
 
-    // interpolated values (readonly)
+    pub fn dye_grid(&self, x: i32, y: i32) -> f64 {
+        self.dye[((x + 1) + (y + 1) * (self.cell_count + 2)) as usize]
+    }
+
+    pub fn dye_grid_mut(&mut self, x: i32, y: i32) -> &mut f64 {
+        &mut self.dye[((x + 1) + (y + 1) * (self.cell_count + 2)) as usize]
+    }
+
+    /// whether cell (x, y) lies inside an obstacle, cells outside the domain never do
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        let cc = self.cell_count;
+        (0..cc).contains(&x) && (0..cc).contains(&y) && self.solid[(x + y * cc) as usize]
+    }
+
+    /// recomputes the solid mask after `obstacles` changed and enforces it
+    pub fn update_obstacles(&mut self) {
+        self.update_solid_mask();
+        self.enforce_obstacles();
+    }
+
+    /// recomputes the solid mask without touching the fields
+    pub fn update_solid_mask(&mut self) {
+        let cc = self.cell_count;
+        self.solid = (0..cc * cc)
+            .map(|i| vector2((i % cc) as f64 + 0.5, (i / cc) as f64 + 0.5))
+            .map(|center| self.obstacles.iter().any(|o| o.contains(center)))
+            .collect();
+    }
+
+    /// obstacles are at rest and hold no dye or heat: zeroes all faces touching and all scalars inside solid cells
+    pub fn enforce_obstacles(&mut self) {
+        let cc = self.cell_count;
+
+        for y in 0..cc {
+            for x in 0..cc {
+                if !self.is_solid(x, y) {
+                    continue;
+                }
+
+                *self.vel_x_grid_mut(x, y) = 0.0;
+                *self.vel_x_grid_mut(x + 1, y) = 0.0;
+                *self.vel_y_grid_mut(x, y) = 0.0;
+                *self.vel_y_grid_mut(x, y + 1) = 0.0;
+                *self.temp_grid_mut(x, y) = 0.0;
+                *self.dye_grid_mut(x, y) = 0.0;
+            }
+        }
+    }
+
+    /// value of the lattice `get` at `(x, y)`, continued beyond `[-1, max_x] x [-1, max_y]` according to the boundary policy
+    #[inline(always)]
+    fn bounded(&self, x: i32, y: i32, max_x: i32, max_y: i32, get: impl Fn(i32, i32) -> f64) -> f64 {
+        let cc = self.cell_count;
+
+        match self.boundary {
+            BoundaryPolicy::Clamp => get(x.clamp(-1, max_x), y.clamp(-1, max_y)),
+            BoundaryPolicy::Zero => {
+                if (-1..=max_x).contains(&x) && (-1..=max_y).contains(&y) { get(x, y) } else { 0.0 }
+            },
+            BoundaryPolicy::Periodic => get(x.rem_euclid(cc), y.rem_euclid(cc))
+        }
+    }
+
+    // lattice samplers (x, y are grid indices as in the accessors above)
+    fn vel_x_sampler(&self) -> impl Fn(i32, i32) -> f64 + '_ {
+        let cc = self.cell_count;
+        move |x, y| self.bounded(x, y, cc + 1, cc, |x, y| self.vel_x_grid(x, y))
+    }
+
+    fn vel_y_sampler(&self) -> impl Fn(i32, i32) -> f64 + '_ {
+        let cc = self.cell_count;
+        move |x, y| self.bounded(x, y, cc, cc + 1, |x, y| self.vel_y_grid(x, y))
+    }
+
+    fn temp_sampler(&self) -> impl Fn(i32, i32) -> f64 + '_ {
+        let cc = self.cell_count;
+        move |x, y| self.bounded(x, y, cc, cc, |x, y| self.temp_grid(x, y))
+    }
+
+    fn dye_sampler(&self) -> impl Fn(i32, i32) -> f64 + '_ {
+        let cc = self.cell_count;
+        move |x, y| self.bounded(x, y, cc, cc, |x, y| self.dye_grid(x, y))
+    }
+
+    /// maps a world-space position into the range the samplers are evaluated on; far-away
+    /// positions are pulled in to just beyond the reach of the widest kernel since the
+    /// boundary policy determines their values anyway
+    fn domain_position(&self, pos: Vector2) -> Vector2 {
+        let cc = self.cell_count as f64;
+
+        match self.boundary {
+            BoundaryPolicy::Periodic => vector2(pos.x.rem_euclid(cc), pos.y.rem_euclid(cc)),
+            _ => pos.clamp(-(MAX_WIDTH as f64), cc + MAX_WIDTH as f64)
+        }
+    }
+
+    // interpolated values (readonly) at world-space positions
+    // world space is measured in cells, the domain spans [0, cell_count]² and cell (x, y) covers [x, x + 1] x [y, y + 1].
+    // x velocities live at (x, y + 0.5), y velocities at (x + 0.5, y), temperature at (x + 0.5, y + 0.5)
     pub fn temp(&self, pos: Vector2) -> f64 {
-        let cc2 = self.cell_count as usize + 2;
+        let pos = self.domain_position(pos);
+        self.temp_interpolation.interpolate_2d(&self.temp_sampler(), pos.x - 0.5, pos.y - 0.5)
+    }
 
-        let start = (pos.y + 0.5) as usize * cc2;
-        // TODO proper boundary condition
-        let zero = vec![0f64; cc2];
-        let row_above = self.temperature.get(start..(start + cc2)).unwrap_or(&zero);
-        let row_below = self.temperature.get((start + cc2)..(start + 2 * cc2)).unwrap_or(&zero);
+    pub fn dye(&self, pos: Vector2) -> f64 {
+        let pos = self.domain_position(pos);
+        self.temp_interpolation.interpolate_2d(&self.dye_sampler(), pos.x - 0.5, pos.y - 0.5)
+    }
 
-        let value_above = CubicInterpolation::interpolate(row_above, pos.x + 0.5);
-        let value_below = CubicInterpolation::interpolate(row_below, pos.x + 0.5);
+    /// interpolates `values` given per interior cell (row by row, e.g. the pressure) with the scalar kernel;
+    /// beyond the interior the outermost cells repeat, or wrap around in periodic domains
+    pub fn cell_field(&self, values: &[f64], pos: Vector2) -> f64 {
+        let cc = self.cell_count;
+        if values.len() != (cc * cc) as usize {
+            return 0.0;
+        }
 
-        LinearInterpolation::interpolate(&[value_above, value_below], (pos.y + 0.5).fract().abs())
+        let sample = |x: i32, y: i32| match self.boundary {
+            BoundaryPolicy::Periodic => values[(x.rem_euclid(cc) + y.rem_euclid(cc) * cc) as usize],
+            _ => values[(x.clamp(0, cc - 1) + y.clamp(0, cc - 1) * cc) as usize]
+        };
+        let pos = self.domain_position(pos);
+        self.temp_interpolation.interpolate_2d(&sample, pos.x - 0.5, pos.y - 0.5)
     }
 
     pub fn temp_average(&self) -> f64 {
@@ -70,20 +227,141 @@ impl StaggeredMACGrid {
     }
 
     pub fn vel(&self, pos: Vector2) -> Vector2 {
-        let cc3 = self.cell_count as usize + 3;
+        let pos = self.domain_position(pos);
+        let vx = self.vel_interpolation.interpolate_2d(&self.vel_x_sampler(), pos.x, pos.y - 0.5);
+        let vy = self.vel_interpolation.interpolate_2d(&self.vel_y_sampler(), pos.x - 0.5, pos.y);
+        vector2(vx, vy)
+    }
 
-        // TODO make generic
-        // TODO proper boundary condition
-        let zero = vec![0f64; cc3];
-        let iy = cc3 * (pos.y + 1.0) as usize;
-        let slice_x = &self.velocities_x.get(iy..iy + cc3 as usize).unwrap_or(&zero);
+    /// velocity at the center of cell (x, y), averaged from the enclosing faces
+    pub fn vel_center(&self, x: i32, y: i32) -> Vector2 {
+        vector2(
+            (self.vel_x_grid(x, y) + self.vel_x_grid(x + 1, y)) / 2.0,
+            (self.vel_y_grid(x, y) + self.vel_y_grid(x, y + 1)) / 2.0
+        )
+    }
 
-        let ix = cc3 * (pos.x + 1.0) as usize;
-        let slice_y = &self.velocities_y.get(ix..ix + cc3 as usize).unwrap_or(&zero);
+    /// cell-centered velocities of all interior cells, row by row
+    pub fn cell_centered_velocities(&self) -> Vec<Vector2> {
+        let cc = self.cell_count;
+        (0..cc).flat_map(|y| (0..cc).map(move |x| (x, y))).map(|(x, y)| self.vel_center(x, y)).collect()
+    }
 
-        let vx = CubicInterpolation::interpolate(slice_x, pos.x + 1.0);
-        let vy = CubicInterpolation::interpolate(slice_y, pos.y + 1.0);
-        vector2(vx, vy)
+    /// cell temperatures of all interior cells, row by row
+    pub fn cell_temperatures(&self) -> Vec<f64> {
+        let cc = self.cell_count;
+        (0..cc).flat_map(|y| (0..cc).map(move |x| (x, y))).map(|(x, y)| self.temp_grid(x, y)).collect()
+    }
+
+    /// dye concentrations of all interior cells, row by row
+    pub fn cell_dye(&self) -> Vec<f64> {
+        let cc = self.cell_count;
+        (0..cc).flat_map(|y| (0..cc).map(move |x| (x, y))).map(|(x, y)| self.dye_grid(x, y)).collect()
+    }
+
+    /// world-space centers of a `resolution`² raster covering the domain, row by row
+    pub fn raster_positions(&self, resolution: usize) -> Vec<Vector2> {
+        let pixel_size = self.cell_count as f64 / resolution as f64;
+        (0..resolution * resolution)
+            .map(|i| vector2(((i % resolution) as f64 + 0.5) * pixel_size, ((i / resolution) as f64 + 0.5) * pixel_size))
+            .collect()
+    }
+
+    /// all fields, settings and obstacles
+    pub fn write(&self, w: &mut Writer) {
+        w.u32(self.cell_count as u32);
+        w.u8(index_of(&BoundaryPolicy::ALL, &self.boundary));
+        w.u8(index_of(&Interpolation2DKind::ALL, &self.vel_interpolation));
+        w.u8(index_of(&Interpolation2DKind::ALL, &self.temp_interpolation));
+
+        w.f64s(&self.velocities_x);
+        w.f64s(&self.velocities_y);
+        w.f64s(&self.temperature);
+        w.f64s(&self.dye);
+
+        w.length(self.obstacles.len());
+        for obstacle in &self.obstacles {
+            obstacle.write(w);
+        }
+    }
+
+    pub fn read(r: &mut Reader) -> Result<Self, FormatError> {
+        let cell_count = r.u32()?;
+        if !(1..=1 << 14).contains(&cell_count) {
+            return Err(FormatError::Invalid(format!("cell count {cell_count}")));
+        }
+        let boundary = r.choice(&BoundaryPolicy::ALL, "boundary policy")?;
+        let vel_interpolation = r.choice(&Interpolation2DKind::ALL, "interpolation")?;
+        let temp_interpolation = r.choice(&Interpolation2DKind::ALL, "interpolation")?;
+
+        // the stored arrays can't be larger than the file, so they are checked before anything
+        // is allocated for the cell count, which a corrupt file could make huge
+        let cc2 = cell_count as usize + 2;
+        let mut fields = Vec::with_capacity(4);
+        for (len, name) in [(cc2 * (cc2 + 1), "x velocities"), (cc2 * (cc2 + 1), "y velocities"), (cc2 * cc2, "temperature"), (cc2 * cc2, "dye")] {
+            let values = r.f64s()?;
+            if values.len() != len {
+                return Err(FormatError::Invalid(format!("{} {name} for {cell_count} cells", values.len())));
+            }
+            fields.push(values);
+        }
+        let [velocities_x, velocities_y, temperature, dye]: [Vec<f64>; 4] = fields.try_into().unwrap();
+
+        let obstacles = r.length()?;
+        let obstacles = (0..obstacles).map(|_| Obstacle::read(r)).collect::<Result<_, _>>()?;
+
+        let mut grid = Self {
+            cell_count: cell_count as i32,
+            velocities_x,
+            velocities_y,
+            temperature,
+            dye,
+            vel_interpolation,
+            temp_interpolation,
+            boundary,
+            obstacles,
+            solid: vec![false; (cell_count * cell_count) as usize]
+        };
+        grid.update_solid_mask();
+
+        Ok(grid)
+    }
+
+    // batched interpolation (same results as the scalar versions above up to rounding)
+    pub fn vel_batch(&self, positions: &[Vector2], out: &mut [Vector2]) {
+        let mut values = vec![0.0; positions.len()];
+        let positions: Vec<Vector2> = positions.iter().map(|p| self.domain_position(*p)).collect();
+
+        let xs: Vec<f64> = positions.iter().map(|p| p.x).collect();
+        let ys: Vec<f64> = positions.iter().map(|p| p.y - 0.5).collect();
+        self.vel_interpolation.interpolate_2d_batch(&self.vel_x_sampler(), &xs, &ys, &mut values);
+
+        for (o, &vx) in out.iter_mut().zip(&values) {
+            o.x = vx;
+        }
+
+        let xs: Vec<f64> = positions.iter().map(|p| p.x - 0.5).collect();
+        let ys: Vec<f64> = positions.iter().map(|p| p.y).collect();
+        self.vel_interpolation.interpolate_2d_batch(&self.vel_y_sampler(), &xs, &ys, &mut values);
+
+        for (o, &vy) in out.iter_mut().zip(&values) {
+            o.y = vy;
+        }
+    }
+
+    pub fn temp_batch(&self, positions: &[Vector2], out: &mut [f64]) {
+        self.scalar_batch(&self.temp_sampler(), positions, out);
+    }
+
+    pub fn dye_batch(&self, positions: &[Vector2], out: &mut [f64]) {
+        self.scalar_batch(&self.dye_sampler(), positions, out);
+    }
+
+    fn scalar_batch(&self, sampler: &impl Fn(i32, i32) -> f64, positions: &[Vector2], out: &mut [f64]) {
+        let positions: Vec<Vector2> = positions.iter().map(|p| self.domain_position(*p)).collect();
+        let xs: Vec<f64> = positions.iter().map(|p| p.x - 0.5).collect();
+        let ys: Vec<f64> = positions.iter().map(|p| p.y - 0.5).collect();
+        self.temp_interpolation.interpolate_2d_batch(sampler, &xs, &ys, out);
     }
 }
 
@@ -94,10 +372,10 @@ impl Display for StaggeredMACGrid {
                 let vy = self.velocities_y[y];
                 let vx = self.velocities_x[x];
                 let l = (vx * vx + vy * vy).sqrt();
-                print!("{l:.2} ");
+                write!(f, "{l:.2} ")?;
             }
 
-            println!("");
+            writeln!(f)?;
         }
 
         Ok(())
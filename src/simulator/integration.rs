@@ -0,0 +1,11 @@
+use super::{grid::StaggeredMACGrid, math::Vector2};
+
+/// one classical fourth-order Runge-Kutta step of length `h` through the (frozen) velocity field of `grid`
+pub fn rk4(grid: &StaggeredMACGrid, pos: Vector2, h: f64) -> Vector2 {
+    let k1 = grid.vel(pos);
+    let k2 = grid.vel(pos + (h / 2.0) * k1);
+    let k3 = grid.vel(pos + (h / 2.0) * k2);
+    let k4 = grid.vel(pos + h * k3);
+
+    pos + (h / 6.0) * (k1 + 2.0 * k2 + 2.0 * k3 + k4)
+}
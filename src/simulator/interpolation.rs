@@ -1,5 +1,36 @@
+use std::marker::PhantomData;
+
+use serde::{Serialize, Deserialize};
+
+/// number of samples evaluated together by the batched kernels
+pub const LANES: usize = 4;
+
+/// widest stencil of any kernel
+pub const MAX_WIDTH: usize = 6;
+
 pub trait Interpolation {
+    /// number of samples around the interpolated position the kernel reads
+    /// (`WIDTH / 2` on either side); at most `MAX_WIDTH`
+    const WIDTH: usize = 4;
+
     fn interpolate(points: &[f64], index: f64) -> f64;
+
+    /// interpolate `points` at every index in `indices`, writing the results to `out`
+    fn interpolate_batch(points: &[f64], indices: &[f64], out: &mut [f64]) {
+        for (o, &index) in out.iter_mut().zip(indices) {
+            *o = Self::interpolate(points, index);
+        }
+    }
+
+    /// interpolate `LANES` stencils at once: `p[k][lane]` is the k-th of the `WIDTH` samples of a lane
+    /// and `s[lane]` its fractional offset past sample `WIDTH / 2 - 1`
+    fn interpolate_lanes(p: &[[f64; LANES]; MAX_WIDTH], s: &[f64; LANES], out: &mut [f64; LANES]) {
+        let offset = (Self::WIDTH / 2 - 1) as f64;
+        for lane in 0..LANES {
+            let row: [f64; MAX_WIDTH] = std::array::from_fn(|k| p[k][lane]);
+            out[lane] = Self::interpolate(&row[..Self::WIDTH], offset + s[lane]);
+        }
+    }
 }
 
 pub struct LinearInterpolation { }
@@ -15,22 +46,584 @@ impl Interpolation for LinearInterpolation {
     }
 }
 
+/// indices of the `N` samples around `index` (`N / 2` on either side), clamped to the slice
+#[inline(always)]
+fn stencil<const N: usize>(len: usize, index: f64) -> ([usize; N], f64) {
+    let c = index as usize;
+    let mut indices = [0; N];
+    for (k, i) in indices.iter_mut().enumerate() {
+        *i = (c + k).saturating_sub(N / 2 - 1).min(len - 1);
+    }
+
+    (indices, index.fract())
+}
+
+// shared machinery of the kernels which are a weighted sum of four neighbouring samples
+#[inline(always)]
+fn interpolate_4(points: &[f64], index: f64, weights: fn(f64) -> [f64; 4]) -> f64 {
+    let (c, s) = stencil::<4>(points.len(), index);
+    let w = weights(s);
+
+    w[0] * points[c[0]] + w[1] * points[c[1]] + w[2] * points[c[2]] + w[3] * points[c[3]]
+}
+
+/// weights and accumulation of `LANES` gathered four-sample stencils, lane-wise
+#[inline(always)]
+fn weighted_4_lanes(p: &[[f64; LANES]; MAX_WIDTH], s: &[f64; LANES], out: &mut [f64; LANES], weights: fn(f64) -> [f64; 4]) {
+    for lane in 0..LANES {
+        let w = weights(s[lane]);
+        out[lane] = w[0] * p[0][lane] + w[1] * p[1][lane] + w[2] * p[2][lane] + w[3] * p[3][lane];
+    }
+}
+
+/// evaluates `LANES` samples at once; written so that every step is a
+/// lane-wise operation on fixed-size arrays which the compiler maps to vector registers
+#[inline(always)]
+fn interpolate_4_lanes(points: &[f64], indices: &[f64; LANES], out: &mut [f64; LANES], weights: fn(f64) -> [f64; 4]) {
+    let mut p = [[0.0; LANES]; MAX_WIDTH];
+    let mut s = [0.0; LANES];
+
+    // gather
+    for lane in 0..LANES {
+        let (c, fract) = stencil::<4>(points.len(), indices[lane]);
+        for k in 0..4 {
+            p[k][lane] = points[c[k]];
+        }
+        s[lane] = fract;
+    }
+
+    weighted_4_lanes(&p, &s, out, weights);
+}
+
+#[inline(always)]
+fn interpolate_4_batch_generic(points: &[f64], indices: &[f64], out: &mut [f64], weights: fn(f64) -> [f64; 4]) {
+    let mut index_chunks = indices.chunks_exact(LANES);
+    let mut out_chunks = out.chunks_exact_mut(LANES);
+
+    for (i, o) in (&mut index_chunks).zip(&mut out_chunks) {
+        interpolate_4_lanes(points, i.try_into().unwrap(), o.try_into().unwrap(), weights);
+    }
+
+    // scalar remainder
+    for (o, &index) in out_chunks.into_remainder().iter_mut().zip(index_chunks.remainder()) {
+        *o = interpolate_4(points, index, weights);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn interpolate_4_batch_avx2(points: &[f64], indices: &[f64], out: &mut [f64], weights: fn(f64) -> [f64; 4]) {
+    interpolate_4_batch_generic(points, indices, out, weights)
+}
+
+fn interpolate_4_batch(points: &[f64], indices: &[f64], out: &mut [f64], weights: fn(f64) -> [f64; 4]) {
+    assert!(out.len() >= indices.len());
+    let out = &mut out[..indices.len()];
+
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+        // SAFETY: the required target features were detected at runtime
+        unsafe { interpolate_4_batch_avx2(points, indices, out, weights) };
+        return;
+    }
+
+    interpolate_4_batch_generic(points, indices, out, weights)
+}
+
+/// cubic Lagrange interpolation
 pub struct CubicInterpolation { }
 
+impl CubicInterpolation {
+    /// weights of the four neighbouring samples for the fractional offset `s`
+    #[inline(always)]
+    pub fn weights(s: f64) -> [f64; 4] {
+        let s2 = s * s;
+        let s3 = s2 * s;
+
+        [
+            -s/3.0 + s2/2.0 - s3/6.0,
+            1.0 - s2 + (s3 - s)/2.0,
+            s + (s2 - s3)/2.0,
+            (s3 - s)/6.0
+        ]
+    }
+}
+
 impl Interpolation for CubicInterpolation {
     fn interpolate(points: &[f64], index: f64) -> f64 {
-        let c1 = index as usize;
-        let c0 = if c1 == 0 { 0 } else { c1 - 1 };
-        let c2 = (c1 + 1).min(points.len() - 1);
-        let c3 = (c1 + 2).min(points.len() - 1);
+        interpolate_4(points, index, Self::weights)
+    }
 
+    fn interpolate_batch(points: &[f64], indices: &[f64], out: &mut [f64]) {
+        interpolate_4_batch(points, indices, out, Self::weights)
+    }
+
+    fn interpolate_lanes(p: &[[f64; LANES]; MAX_WIDTH], s: &[f64; LANES], out: &mut [f64; LANES]) {
+        weighted_4_lanes(p, s, out, Self::weights)
+    }
+}
+
+/// Catmull-Rom spline (cubic Hermite with central difference tangents)
+pub struct CatmullRomInterpolation { }
+
+impl CatmullRomInterpolation {
+    /// weights of the four neighbouring samples for the fractional offset `s`
+    #[inline(always)]
+    pub fn weights(s: f64) -> [f64; 4] {
+        let s2 = s * s;
+        let s3 = s2 * s;
+
+        [
+            (-s3 + 2.0 * s2 - s) / 2.0,
+            (3.0 * s3 - 5.0 * s2 + 2.0) / 2.0,
+            (-3.0 * s3 + 4.0 * s2 + s) / 2.0,
+            (s3 - s2) / 2.0
+        ]
+    }
+}
+
+impl Interpolation for CatmullRomInterpolation {
+    fn interpolate(points: &[f64], index: f64) -> f64 {
+        interpolate_4(points, index, Self::weights)
+    }
+
+    fn interpolate_batch(points: &[f64], indices: &[f64], out: &mut [f64]) {
+        interpolate_4_batch(points, indices, out, Self::weights)
+    }
+
+    fn interpolate_lanes(p: &[[f64; LANES]; MAX_WIDTH], s: &[f64; LANES], out: &mut [f64; LANES]) {
+        weighted_4_lanes(p, s, out, Self::weights)
+    }
+}
+
+/// cubic Hermite segment between `p1` and `p2` with end tangents `m1` and `m2`
+#[inline(always)]
+fn hermite(p1: f64, p2: f64, m1: f64, m2: f64, s: f64) -> f64 {
+    let s2 = s * s;
+    let s3 = s2 * s;
+
+    (2.0 * s3 - 3.0 * s2 + 1.0) * p1 + (s3 - 2.0 * s2 + s) * m1 + (3.0 * s2 - 2.0 * s3) * p2 + (s3 - s2) * m2
+}
+
+/// monotonicity-preserving cubic Hermite interpolation (Fritsch-Carlson)
+pub struct MonotoneCubicInterpolation { }
+
+impl Interpolation for MonotoneCubicInterpolation {
+    fn interpolate(points: &[f64], index: f64) -> f64 {
+        let (c, s) = stencil::<4>(points.len(), index);
+        let p = c.map(|i| points[i]);
+
+        let d0 = p[1] - p[0];
+        let d1 = p[2] - p[1];
+        let d2 = p[3] - p[2];
+
+        if d1 == 0.0 {
+            return p[1];
+        }
+
+        // tangents are zero at local extrema, otherwise the mean of the adjacent secants
+        let mut m1 = if d0 * d1 <= 0.0 { 0.0 } else { (d0 + d1) / 2.0 };
+        let mut m2 = if d1 * d2 <= 0.0 { 0.0 } else { (d1 + d2) / 2.0 };
+
+        // restrict the tangents to the region where the segment is monotone
+        let a = m1 / d1;
+        let b = m2 / d1;
+        let r = a * a + b * b;
+        if r > 9.0 {
+            let t = 3.0 / r.sqrt();
+            m1 = t * a * d1;
+            m2 = t * b * d1;
+        }
+
+        hermite(p[1], p[2], m1, m2, s)
+    }
+}
+
+/// cubic Lagrange interpolation clamped to the range of the two enclosing samples
+pub struct ClampedCubicInterpolation { }
+
+impl Interpolation for ClampedCubicInterpolation {
+    fn interpolate(points: &[f64], index: f64) -> f64 {
+        let (c, _) = stencil::<4>(points.len(), index);
+        let (p1, p2) = (points[c[1]], points[c[2]]);
+
+        CubicInterpolation::interpolate(points, index).clamp(p1.min(p2), p1.max(p2))
+    }
+
+    fn interpolate_batch(points: &[f64], indices: &[f64], out: &mut [f64]) {
+        CubicInterpolation::interpolate_batch(points, indices, out);
+
+        for (o, &index) in out.iter_mut().zip(indices) {
+            let (c, _) = stencil::<4>(points.len(), index);
+            let (p1, p2) = (points[c[1]], points[c[2]]);
+            *o = o.clamp(p1.min(p2), p1.max(p2));
+        }
+    }
+
+    fn interpolate_lanes(p: &[[f64; LANES]; MAX_WIDTH], s: &[f64; LANES], out: &mut [f64; LANES]) {
+        CubicInterpolation::interpolate_lanes(p, s, out);
+
+        for lane in 0..LANES {
+            let (p1, p2) = (p[1][lane], p[2][lane]);
+            out[lane] = out[lane].clamp(p1.min(p2), p1.max(p2));
+        }
+    }
+}
+
+/// weighted essentially non-oscillatory interpolation from two quadratic candidates on four samples;
+/// matches the cubic Lagrange kernel where the data is smooth and falls back to the smoother
+/// candidate near discontinuities
+pub struct Weno4Interpolation { }
+
+/// regularization of the WENO smoothness indicators
+const WENO_EPSILON: f64 = 1e-30;
+
+/// nonlinear WENO-Z combination of the `candidates` with optimal weights `linear` and smoothness indicators `beta`;
+/// the global indicator `tau` keeps the weights close to optimal wherever all candidates are equally smooth
+#[inline(always)]
+fn weno_combine<const K: usize>(candidates: [f64; K], linear: [f64; K], beta: [f64; K]) -> f64 {
+    let tau = (beta[0] - beta[K - 1]).abs();
+    let alpha: [f64; K] = std::array::from_fn(|k| linear[k] * (1.0 + (tau / (beta[k] + WENO_EPSILON)).powi(2)));
+    let sum: f64 = alpha.iter().sum();
+
+    candidates.iter().zip(alpha).map(|(q, a)| q * a / sum).sum()
+}
+
+impl Interpolation for Weno4Interpolation {
+    fn interpolate(points: &[f64], index: f64) -> f64 {
+        let (c, s) = stencil::<4>(points.len(), index);
+        let p = c.map(|i| points[i]);
+
+        // quadratics through the nodes -1, 0, 1 and 0, 1, 2
+        let q0 = p[1] + s * (p[2] - p[0]) / 2.0 + s * s * (p[0] - 2.0 * p[1] + p[2]) / 2.0;
+        let q1 = p[1] + s * (-3.0 * p[1] + 4.0 * p[2] - p[3]) / 2.0 + s * s * (p[1] - 2.0 * p[2] + p[3]) / 2.0;
+
+        let beta0 = (p[0] - 2.0 * p[1] + p[2]).powi(2);
+        let beta1 = (p[1] - 2.0 * p[2] + p[3]).powi(2);
+
+        weno_combine([q0, q1], [(2.0 - s) / 3.0, (1.0 + s) / 3.0], [beta0, beta1])
+    }
+}
+
+/// weighted essentially non-oscillatory interpolation from three cubic candidates on six samples;
+/// matches the quintic Lagrange kernel where the data is smooth
+pub struct Weno6Interpolation { }
+
+impl Interpolation for Weno6Interpolation {
+    const WIDTH: usize = 6;
+
+    fn interpolate(points: &[f64], index: f64) -> f64 {
+        let (c, s) = stencil::<6>(points.len(), index);
+        let p = c.map(|i| points[i]);
+
+        // cubics through the nodes -2..=1, -1..=2 and 0..=3, expressed through the Lagrange weights
+        // of the four-point kernel shifted to the respective stencil
+        let q0 = CubicInterpolation::weights(s + 1.0).iter().zip(&p[0..4]).map(|(w, p)| w * p).sum::<f64>();
+        let q1 = CubicInterpolation::weights(s).iter().zip(&p[1..5]).map(|(w, p)| w * p).sum::<f64>();
+        let q2 = CubicInterpolation::weights(s - 1.0).iter().zip(&p[2..6]).map(|(w, p)| w * p).sum::<f64>();
+
+        // squared second and third differences of every candidate stencil
+        let beta = [0, 1, 2].map(|k| {
+            let q = &p[k..k + 4];
+            let d2a = q[0] - 2.0 * q[1] + q[2];
+            let d2b = q[1] - 2.0 * q[2] + q[3];
+            let d3 = q[3] - 3.0 * q[2] + 3.0 * q[1] - q[0];
+            d2a * d2a + d2b * d2b + d3 * d3
+        });
+
+        let linear = [
+            (2.0 - s) * (3.0 - s) / 20.0,
+            (3.0 - s) * (s + 2.0) / 10.0,
+            (s + 2.0) * (s + 1.0) / 20.0
+        ];
+
+        weno_combine([q0, q1, q2], linear, beta)
+    }
+}
+
+/// interpolating cubic B-spline; the samples are prefiltered into spline coefficients
+/// (mirrored at the ends) so that the spline passes through every sample.
+/// the prefilter is global, so this is a 1D kernel only and not offered for the windowed 2D tensor product;
+/// rows evaluated many times should be prefiltered once with `coefficients` and sampled with `evaluate`
+pub struct CubicBSplineInterpolation { }
+
+impl CubicBSplineInterpolation {
+    /// pole of the cubic B-spline prefilter
+    const POLE: f64 = -0.2679491924311228; // sqrt(3) - 2
+
+    /// weights of the four neighbouring coefficients for the fractional offset `s`
+    #[inline(always)]
+    pub fn weights(s: f64) -> [f64; 4] {
+        let s2 = s * s;
+        let s3 = s2 * s;
+        let t = 1.0 - s;
+
+        [
+            t * t * t / 6.0,
+            (3.0 * s3 - 6.0 * s2 + 4.0) / 6.0,
+            (-3.0 * s3 + 3.0 * s2 + 3.0 * s + 1.0) / 6.0,
+            s3 / 6.0
+        ]
+    }
+
+    /// spline coefficients of `points` (recursive filtering with mirror boundaries)
+    pub fn coefficients(points: &[f64]) -> Vec<f64> {
+        let n = points.len();
+        let z = Self::POLE;
+        let mut c: Vec<f64> = points.iter().map(|p| 6.0 * p).collect();
+
+        if n < 2 {
+            return points.to_vec();
+        }
+
+        // causal initialization for mirror-symmetric extension
+        let mut zn = z;
+        let mut z2n = z.powi(n as i32 - 1);
+        let mut sum = c[0] + z2n * c[n - 1];
+        z2n *= z2n / z;
+        for ck in &c[1..n - 1] {
+            sum += (zn + z2n) * ck;
+            zn *= z;
+            z2n /= z;
+        }
+        c[0] = sum / (1.0 - zn * zn);
+
+        for k in 1..n {
+            c[k] += z * c[k - 1];
+        }
+
+        // anti-causal pass
+        c[n - 1] = (z / (z * z - 1.0)) * (z * c[n - 2] + c[n - 1]);
+        for k in (0..n - 1).rev() {
+            c[k] = z * (c[k + 1] - c[k]);
+        }
+
+        c
+    }
+
+    /// value at `index` of the spline with the given `coefficients`
+    #[inline(always)]
+    pub fn evaluate(coefficients: &[f64], index: f64) -> f64 {
+        let n = coefficients.len() as i64;
+        let c = index as i64;
         let s = index.fract();
 
-        let w0 = -s/3.0 + s.powi(2)/2.0 - s.powi(3)/6.0;
-        let w1 = 1.0 - s.powi(2) + (s.powi(3) - s)/2.0;
-        let w2 = s + (s.powi(2) - s.powi(3))/2.0;
-        let w3 = (s.powi(3) - s)/6.0;
+        // mirror the coefficients at the ends
+        let mirror = |i: i64| {
+            let i = if i < 0 { -i } else if i >= n { 2 * (n - 1) - i } else { i };
+            coefficients[i.clamp(0, n - 1) as usize]
+        };
+
+        let w = Self::weights(s);
+        w[0] * mirror(c - 1) + w[1] * mirror(c) + w[2] * mirror(c + 1) + w[3] * mirror(c + 2)
+    }
+}
+
+impl Interpolation for CubicBSplineInterpolation {
+    fn interpolate(points: &[f64], index: f64) -> f64 {
+        Self::evaluate(&Self::coefficients(points), index)
+    }
+
+    fn interpolate_batch(points: &[f64], indices: &[f64], out: &mut [f64]) {
+        // prefilter only once for all indices
+        let coefficients = Self::coefficients(points);
+
+        for (o, &index) in out.iter_mut().zip(indices) {
+            *o = Self::evaluate(&coefficients, index);
+        }
+    }
+}
+
+/// interpolation on a 2D lattice; `sample(i, j)` returns the value stored at integer lattice
+/// coordinates and has to handle out-of-range indices itself
+pub trait Interpolation2D {
+    fn interpolate_2d<F: Fn(i32, i32) -> f64>(sample: &F, x: f64, y: f64) -> f64;
+
+    /// interpolate at every `(xs[i], ys[i])`, writing the results to `out`
+    fn interpolate_2d_batch<F: Fn(i32, i32) -> f64>(sample: &F, xs: &[f64], ys: &[f64], out: &mut [f64]) {
+        for ((o, &x), &y) in out.iter_mut().zip(xs).zip(ys) {
+            *o = Self::interpolate_2d(sample, x, y);
+        }
+    }
+}
+
+#[inline(always)]
+fn split(v: f64) -> (i32, f64) {
+    (v.floor() as i32, v - v.floor())
+}
+
+pub struct BilinearInterpolation { }
+
+impl Interpolation2D for BilinearInterpolation {
+    fn interpolate_2d<F: Fn(i32, i32) -> f64>(sample: &F, x: f64, y: f64) -> f64 {
+        let (i, sx) = split(x);
+        let (j, sy) = split(y);
+
+        let below = LinearInterpolation::interpolate(&[sample(i, j), sample(i + 1, j)], sx);
+        let above = LinearInterpolation::interpolate(&[sample(i, j + 1), sample(i + 1, j + 1)], sx);
+
+        LinearInterpolation::interpolate(&[below, above], sy)
+    }
+}
+
+/// separable 2D interpolation applying the kernel `I` over the `I::WIDTH`² neighbourhood,
+/// first along x for every row and then along y
+pub struct TensorProductInterpolation<I: Interpolation> {
+    kernel: PhantomData<I>
+}
+
+impl<I: Interpolation> Interpolation2D for TensorProductInterpolation<I> {
+    fn interpolate_2d<F: Fn(i32, i32) -> f64>(sample: &F, x: f64, y: f64) -> f64 {
+        let w = I::WIDTH;
+        let offset = (w / 2 - 1) as i32;
+
+        let (i, sx) = split(x);
+        let (j, sy) = split(y);
+
+        let mut row = [0.0; MAX_WIDTH];
+        let mut column = [0.0; MAX_WIDTH];
+        for (dj, c) in column[..w].iter_mut().enumerate() {
+            let j = j - offset + dj as i32;
+            for (di, r) in row[..w].iter_mut().enumerate() {
+                *r = sample(i - offset + di as i32, j);
+            }
+            *c = I::interpolate(&row[..w], offset as f64 + sx);
+        }
+
+        I::interpolate(&column[..w], offset as f64 + sy)
+    }
+
+    fn interpolate_2d_batch<F: Fn(i32, i32) -> f64>(sample: &F, xs: &[f64], ys: &[f64], out: &mut [f64]) {
+        assert!(xs.len() == ys.len() && out.len() >= xs.len());
+        let out = &mut out[..xs.len()];
+
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+            // SAFETY: the required target features were detected at runtime
+            unsafe { tensor_product_batch_avx2::<I, F>(sample, xs, ys, out) };
+            return;
+        }
+
+        tensor_product_batch_generic::<I, F>(sample, xs, ys, out)
+    }
+}
+
+/// `LANES` neighbourhoods at a time are gathered into fixed-size arrays on the stack,
+/// with the cell indices kept as integers and only the fractional offsets passed to the kernel
+#[inline(always)]
+fn tensor_product_batch_generic<I: Interpolation, F: Fn(i32, i32) -> f64>(sample: &F, xs: &[f64], ys: &[f64], out: &mut [f64]) {
+    let w = I::WIDTH;
+    let offset = (w / 2 - 1) as i32;
+
+    let mut x_chunks = xs.chunks_exact(LANES);
+    let mut y_chunks = ys.chunks_exact(LANES);
+    let mut out_chunks = out.chunks_exact_mut(LANES);
+
+    for ((xs, ys), out) in (&mut x_chunks).zip(&mut y_chunks).zip(&mut out_chunks) {
+        let (mut i, mut sx) = ([0; LANES], [0.0; LANES]);
+        let (mut j, mut sy) = ([0; LANES], [0.0; LANES]);
+        for lane in 0..LANES {
+            (i[lane], sx[lane]) = split(xs[lane]);
+            (j[lane], sy[lane]) = split(ys[lane]);
+        }
+
+        let mut row = [[0.0; LANES]; MAX_WIDTH];
+        let mut column = [[0.0; LANES]; MAX_WIDTH];
+        for (dj, c) in column[..w].iter_mut().enumerate() {
+            for (di, r) in row[..w].iter_mut().enumerate() {
+                for lane in 0..LANES {
+                    r[lane] = sample(i[lane] - offset + di as i32, j[lane] - offset + dj as i32);
+                }
+            }
+            I::interpolate_lanes(&row, &sx, c);
+        }
+
+        I::interpolate_lanes(&column, &sy, out.try_into().unwrap());
+    }
+
+    // scalar remainder
+    let remainder = x_chunks.remainder().iter().zip(y_chunks.remainder());
+    for (o, (&x, &y)) in out_chunks.into_remainder().iter_mut().zip(remainder) {
+        *o = TensorProductInterpolation::<I>::interpolate_2d(sample, x, y);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,fma")]
+unsafe fn tensor_product_batch_avx2<I: Interpolation, F: Fn(i32, i32) -> f64>(sample: &F, xs: &[f64], ys: &[f64], out: &mut [f64]) {
+    tensor_product_batch_generic::<I, F>(sample, xs, ys, out)
+}
+
+/// Catmull-Rom bicubic interpolation
+pub type BicubicInterpolation = TensorProductInterpolation<CatmullRomInterpolation>;
+
+/// bicubic interpolation with the cubic Lagrange kernel along each axis
+pub type LagrangeBicubicInterpolation = TensorProductInterpolation<CubicInterpolation>;
+
+/// bicubic interpolation built from monotone cubics, stays within the values of the enclosing cell
+pub type MonotoneBicubicInterpolation = TensorProductInterpolation<MonotoneCubicInterpolation>;
+
+/// bicubic interpolation built from clamped cubics, stays within the values of the enclosing cell
+pub type ClampedBicubicInterpolation = TensorProductInterpolation<ClampedCubicInterpolation>;
+
+/// tensor product of WENO4 kernels
+pub type Weno4Interpolation2D = TensorProductInterpolation<Weno4Interpolation>;
+
+/// tensor product of WENO6 kernels
+pub type Weno6Interpolation2D = TensorProductInterpolation<Weno6Interpolation>;
+
+/// runtime selection of a 2D interpolation kernel, e.g. per grid field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation2DKind {
+    Bilinear,
+    Bicubic,
+    LagrangeBicubic,
+    MonotoneBicubic,
+    ClampedBicubic,
+    Weno4,
+    Weno6
+}
+
+impl Interpolation2DKind {
+    pub const ALL: [Interpolation2DKind; 7] = [
+        Self::Bilinear, Self::Bicubic, Self::LagrangeBicubic, Self::MonotoneBicubic, Self::ClampedBicubic,
+        Self::Weno4, Self::Weno6
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bilinear => "bilinear",
+            Self::Bicubic => "bicubic (Catmull-Rom)",
+            Self::LagrangeBicubic => "bicubic (Lagrange)",
+            Self::MonotoneBicubic => "monotone bicubic",
+            Self::ClampedBicubic => "clamped bicubic",
+            Self::Weno4 => "WENO4",
+            Self::Weno6 => "WENO6"
+        }
+    }
+
+    pub fn interpolate_2d<F: Fn(i32, i32) -> f64>(&self, sample: &F, x: f64, y: f64) -> f64 {
+        match self {
+            Self::Bilinear => BilinearInterpolation::interpolate_2d(sample, x, y),
+            Self::Bicubic => BicubicInterpolation::interpolate_2d(sample, x, y),
+            Self::LagrangeBicubic => LagrangeBicubicInterpolation::interpolate_2d(sample, x, y),
+            Self::MonotoneBicubic => MonotoneBicubicInterpolation::interpolate_2d(sample, x, y),
+            Self::ClampedBicubic => ClampedBicubicInterpolation::interpolate_2d(sample, x, y),
+            Self::Weno4 => Weno4Interpolation2D::interpolate_2d(sample, x, y),
+            Self::Weno6 => Weno6Interpolation2D::interpolate_2d(sample, x, y)
+        }
+    }
 
-        w0 * points[c0] + w1 * points[c1] + w2 * points[c2] + w3 * points[c3]
+    pub fn interpolate_2d_batch<F: Fn(i32, i32) -> f64>(&self, sample: &F, xs: &[f64], ys: &[f64], out: &mut [f64]) {
+        match self {
+            Self::Bilinear => BilinearInterpolation::interpolate_2d_batch(sample, xs, ys, out),
+            Self::Bicubic => BicubicInterpolation::interpolate_2d_batch(sample, xs, ys, out),
+            Self::LagrangeBicubic => LagrangeBicubicInterpolation::interpolate_2d_batch(sample, xs, ys, out),
+            Self::MonotoneBicubic => MonotoneBicubicInterpolation::interpolate_2d_batch(sample, xs, ys, out),
+            Self::ClampedBicubic => ClampedBicubicInterpolation::interpolate_2d_batch(sample, xs, ys, out),
+            Self::Weno4 => Weno4Interpolation2D::interpolate_2d_batch(sample, xs, ys, out),
+            Self::Weno6 => Weno6Interpolation2D::interpolate_2d_batch(sample, xs, ys, out)
+        }
     }
 }
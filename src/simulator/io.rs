@@ -0,0 +1,199 @@
+use std::{fmt::Display, fs, io, path::Path};
+
+use super::math::{vector2, Vector2};
+
+/// why a file could not be read
+#[derive(Debug)]
+pub enum FormatError {
+    Io(io::Error),
+    /// the file does not start with the expected magic bytes
+    WrongKind { expected: &'static str },
+    UnsupportedVersion(u32),
+    /// the file ends before all values were read
+    Truncated,
+    /// array dimensions that don't fit the grid
+    Shape { expected: Vec<usize>, found: Vec<usize> },
+    /// array element type that can't be read
+    DType(String),
+    Invalid(String)
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::WrongKind { expected } => write!(f, "not a {expected} file"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported format version {v}"),
+            Self::Truncated => write!(f, "file is truncated"),
+            Self::Shape { expected, found } => write!(f, "expected shape {expected:?}, found {found:?}"),
+            Self::DType(dtype) => write!(f, "unsupported dtype {dtype}, expected floats or integers"),
+            Self::Invalid(reason) => write!(f, "invalid file: {reason}")
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<io::Error> for FormatError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// little-endian binary encoder; floats are stored bit for bit
+pub struct Writer {
+    buf: Vec<u8>
+}
+
+impl Writer {
+    /// starts a file of the given kind (8 magic bytes) and format version
+    pub fn new(magic: &[u8; 8], version: u32) -> Self {
+        let mut writer = Self { buf: magic.to_vec() };
+        writer.u32(version);
+        writer
+    }
+
+    pub fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    pub fn bool(&mut self, v: bool) {
+        self.u8(v as u8);
+    }
+
+    pub fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, v: f64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub fn length(&mut self, v: usize) {
+        self.u64(v as u64);
+    }
+
+    pub fn vector2(&mut self, v: Vector2) {
+        self.f64(v.x);
+        self.f64(v.y);
+    }
+
+    pub fn str(&mut self, v: &str) {
+        self.length(v.len());
+        self.buf.extend_from_slice(v.as_bytes());
+    }
+
+    pub fn f64s(&mut self, v: &[f64]) {
+        self.length(v.len());
+        for x in v {
+            self.f64(*x);
+        }
+    }
+
+    /// writes to a temporary file next to `path` first, so an interrupted write never leaves a broken file behind
+    pub fn save(self, path: &Path) -> io::Result<()> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        fs::write(&temporary, &self.buf)?;
+        fs::rename(&temporary, path)
+    }
+}
+
+/// decoder for files written by `Writer`
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Reader<'a> {
+    /// checks the magic bytes of `kind` and returns the reader and the format version
+    pub fn new(data: &'a [u8], magic: &[u8; 8], kind: &'static str) -> Result<(Self, u32), FormatError> {
+        if data.len() < 8 || &data[..8] != magic {
+            return Err(FormatError::WrongKind { expected: kind });
+        }
+
+        let mut reader = Self { data, pos: 8 };
+        let version = reader.u32()?;
+        Ok((reader, version))
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], FormatError> {
+        let bytes = self.data.get(self.pos..self.pos + N).ok_or(FormatError::Truncated)?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> Result<u8, FormatError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, FormatError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            v => Err(FormatError::Invalid(format!("{v} is not a boolean")))
+        }
+    }
+
+    pub fn u32(&mut self) -> Result<u32, FormatError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, FormatError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, FormatError> {
+        Ok(f64::from_le_bytes(self.take()?))
+    }
+
+    /// a length, which can't exceed the remaining bytes (so corrupt files don't allocate huge buffers)
+    pub fn length(&mut self) -> Result<usize, FormatError> {
+        let len = self.u64()?;
+        if len > (self.data.len() - self.pos) as u64 {
+            return Err(FormatError::Truncated);
+        }
+        Ok(len as usize)
+    }
+
+    pub fn vector2(&mut self) -> Result<Vector2, FormatError> {
+        Ok(vector2(self.f64()?, self.f64()?))
+    }
+
+    pub fn str(&mut self) -> Result<String, FormatError> {
+        let len = self.length()?;
+        let bytes = self.data[self.pos..self.pos + len].to_vec();
+        self.pos += len;
+        String::from_utf8(bytes).map_err(|_| FormatError::Invalid(String::from("string is not UTF-8")))
+    }
+
+    pub fn f64s(&mut self) -> Result<Vec<f64>, FormatError> {
+        let len = self.length()?;
+        (0..len).map(|_| self.f64()).collect()
+    }
+
+    /// index into `values`, e.g. one of the `ALL` arrays of an enum
+    pub fn choice<T: Copy>(&mut self, values: &[T], what: &str) -> Result<T, FormatError> {
+        let i = self.u8()? as usize;
+        values.get(i).copied().ok_or_else(|| FormatError::Invalid(format!("unknown {what} {i}")))
+    }
+
+    /// fails unless everything was read
+    pub fn finish(self) -> Result<(), FormatError> {
+        if self.pos == self.data.len() {
+            Ok(())
+        } else {
+            Err(FormatError::Invalid(format!("{} unexpected trailing bytes", self.data.len() - self.pos)))
+        }
+    }
+}
+
+/// position of `value` in `values` for `Reader::choice`
+pub fn index_of<T: PartialEq>(values: &[T], value: &T) -> u8 {
+    values.iter().position(|v| v == value).expect("value missing from its ALL array") as u8
+}
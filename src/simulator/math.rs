@@ -1,6 +1,9 @@
 use epaint::{Vec2, vec2};
+use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Copy)]
+/// stored as `[x, y]` in scenario files
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "[f64; 2]", into = "[f64; 2]")]
 pub struct Vector2 {
     pub x: f64,
     pub y: f64
@@ -48,6 +51,18 @@ impl std::ops::Sub<Vector2> for Vector2 {
     }
 }
 
+impl From<[f64; 2]> for Vector2 {
+    fn from([x, y]: [f64; 2]) -> Self {
+        vector2(x, y)
+    }
+}
+
+impl From<Vector2> for [f64; 2] {
+    fn from(value: Vector2) -> Self {
+        [value.x, value.y]
+    }
+}
+
 impl From<Vector2> for Vec2 {
     fn from(value: Vector2) -> Self {
         vec2(value.x as f32, value.y as f32)
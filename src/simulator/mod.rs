@@ -1,4 +1,21 @@
 pub mod grid;
+#[allow(clippy::module_inception)]
 pub mod simulator;
 pub mod math;
 pub mod interpolation;
+pub mod integration;
+pub mod particles;
+pub mod pressure;
+pub mod derived;
+pub mod brush;
+pub mod obstacles;
+pub mod diagnostics;
+pub mod probes;
+pub mod io;
+pub mod checkpoint;
+pub mod snapshot;
+pub mod vtk;
+pub mod arrays;
+pub mod scenario;
+pub mod forces;
+pub mod expression;
@@ -0,0 +1,116 @@
+use serde::{Serialize, Deserialize};
+
+use super::{math::{vector2, Vector2}, io::{Writer, Reader, FormatError}};
+
+/// solid region of the domain, positions in world space
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Obstacle {
+    Rectangle { min: Vector2, max: Vector2 },
+    Circle { center: Vector2, radius: f64 },
+    /// closed by connecting the last point to the first
+    Polygon { points: Vec<Vector2> }
+}
+
+impl Obstacle {
+    /// axis-aligned rectangle spanned by two opposite corners
+    pub fn rectangle(a: Vector2, b: Vector2) -> Self {
+        Self::Rectangle { min: vector2(a.x.min(b.x), a.y.min(b.y)), max: vector2(a.x.max(b.x), a.y.max(b.y)) }
+    }
+
+    pub fn contains(&self, p: Vector2) -> bool {
+        match self {
+            Self::Rectangle { min, max } => p.x >= min.x && p.x <= max.x && p.y >= min.y && p.y <= max.y,
+            Self::Circle { center, radius } => (p - *center).len_squared() <= radius * radius,
+            Self::Polygon { points } => {
+                // even-odd rule
+                let mut inside = false;
+                for (i, a) in points.iter().enumerate() {
+                    let b = points[(i + 1) % points.len()];
+                    if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
+
+    /// axis-aligned bounding box as (min, max)
+    pub fn bounds(&self) -> (Vector2, Vector2) {
+        match self {
+            Self::Rectangle { min, max } => (*min, *max),
+            Self::Circle { center, radius } => (*center - vector2(*radius, *radius), *center + vector2(*radius, *radius)),
+            Self::Polygon { points } => points.iter().fold(
+                (vector2(f64::INFINITY, f64::INFINITY), vector2(f64::NEG_INFINITY, f64::NEG_INFINITY)),
+                |(min, max), p| (vector2(min.x.min(p.x), min.y.min(p.y)), vector2(max.x.max(p.x), max.y.max(p.y)))
+            )
+        }
+    }
+
+    pub fn translate(&mut self, offset: Vector2) {
+        match self {
+            Self::Rectangle { min, max } => {
+                *min = *min + offset;
+                *max = *max + offset;
+            },
+            Self::Circle { center, .. } => *center = *center + offset,
+            Self::Polygon { points } => points.iter_mut().for_each(|p| *p = *p + offset)
+        }
+    }
+
+    /// stretches the obstacle so its bounding box becomes `min`..`max` (circles stay round and fit inside)
+    pub fn resize(&mut self, min: Vector2, max: Vector2) {
+        let (old_min, old_max) = self.bounds();
+        let size = old_max - old_min;
+        let map = |p: Vector2| {
+            let t = vector2(
+                if size.x > 0.0 { (p.x - old_min.x) / size.x } else { 0.0 },
+                if size.y > 0.0 { (p.y - old_min.y) / size.y } else { 0.0 }
+            );
+            vector2(min.x + t.x * (max.x - min.x), min.y + t.y * (max.y - min.y))
+        };
+
+        match self {
+            Self::Rectangle { .. } => *self = Self::rectangle(min, max),
+            Self::Circle { center, radius } => {
+                *center = 0.5 * (min + max);
+                *radius = 0.5 * (max.x - min.x).abs().min((max.y - min.y).abs());
+            },
+            Self::Polygon { points } => points.iter_mut().for_each(|p| *p = map(*p))
+        }
+    }
+
+    pub fn write(&self, w: &mut Writer) {
+        match self {
+            Self::Rectangle { min, max } => {
+                w.u8(0);
+                w.vector2(*min);
+                w.vector2(*max);
+            },
+            Self::Circle { center, radius } => {
+                w.u8(1);
+                w.vector2(*center);
+                w.f64(*radius);
+            },
+            Self::Polygon { points } => {
+                w.u8(2);
+                w.length(points.len());
+                points.iter().for_each(|p| w.vector2(*p));
+            }
+        }
+    }
+
+    pub fn read(r: &mut Reader) -> Result<Self, FormatError> {
+        match r.u8()? {
+            0 => Ok(Self::Rectangle { min: r.vector2()?, max: r.vector2()? }),
+            1 => Ok(Self::Circle { center: r.vector2()?, radius: r.f64()? }),
+            2 => {
+                let len = r.length()?;
+                let points = (0..len).map(|_| r.vector2()).collect::<Result<_, _>>()?;
+                Ok(Self::Polygon { points })
+            },
+            tag => Err(FormatError::Invalid(format!("unknown obstacle shape {tag}")))
+        }
+    }
+}
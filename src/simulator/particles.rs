@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+
+use super::{grid::{StaggeredMACGrid, BoundaryPolicy}, integration::rk4, math::{vector2, Vector2}, io::{Writer, Reader, FormatError}};
+
+/// massless tracer particle, positions in world space
+#[derive(Debug, Clone, PartialEq)]
+pub struct Particle {
+    pub pos: Vector2,
+    pub age: f64,
+    /// previous positions, most recent last
+    pub trail: VecDeque<Vector2>
+}
+
+/// continuously releases particles around `pos`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emitter {
+    pub pos: Vector2,
+    /// particles per unit of simulation time
+    pub rate: f64,
+    /// particles are released uniformly within this radius
+    pub radius: f64,
+
+    // fractional particles carried over between steps
+    pending: f64
+}
+
+impl Emitter {
+    pub fn new(pos: Vector2, rate: f64, radius: f64) -> Self {
+        Self { pos, rate, radius, pending: 0.0 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Particles {
+    pub particles: Vec<Particle>,
+    pub emitters: Vec<Emitter>,
+
+    /// particles older than this (in simulation time) are removed
+    pub lifetime: f64,
+    /// emitters pause while this many particles are alive
+    pub max_count: usize,
+    /// number of previous positions kept per particle
+    pub trail_length: usize,
+
+    rng_state: u64
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Particles {
+    pub fn new() -> Self {
+        Self {
+            particles: Vec::new(),
+            emitters: Vec::new(),
+            lifetime: 50.0,
+            max_count: 5000,
+            trail_length: 20,
+            rng_state: 0x9e3779b97f4a7c15
+        }
+    }
+
+    /// uniform in [0, 1) (xorshift64*)
+    fn random(&mut self) -> f64 {
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        (self.rng_state.wrapping_mul(0x2545f4914f6cdd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// uniform within the disk of `radius` around `center`
+    fn random_in_disk(&mut self, center: Vector2, radius: f64) -> Vector2 {
+        let r = radius * self.random().sqrt();
+        let phi = 2.0 * std::f64::consts::PI * self.random();
+        center + vector2(r * phi.cos(), r * phi.sin())
+    }
+
+    pub fn add(&mut self, pos: Vector2) {
+        self.particles.push(Particle { pos, age: 0.0, trail: VecDeque::new() });
+    }
+
+    /// `count` particles uniformly within `radius` around `center`
+    pub fn add_cluster(&mut self, center: Vector2, radius: f64, count: usize) {
+        for _ in 0..count {
+            let pos = self.random_in_disk(center, radius);
+            self.add(pos);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.emitters.clear();
+    }
+
+    /// advects all particles by `dt` through the velocity field of `grid`, ages and
+    /// removes them, and releases new particles from the emitters; particles leaving the domain
+    /// are removed, or wrapped around in periodic domains
+    pub fn step(&mut self, grid: &StaggeredMACGrid, dt: f64) {
+        let trail_length = self.trail_length;
+        let cc = grid.cell_count as f64;
+        let periodic = grid.boundary == BoundaryPolicy::Periodic;
+
+        for particle in &mut self.particles {
+            particle.trail.push_back(particle.pos);
+            while particle.trail.len() > trail_length {
+                particle.trail.pop_front();
+            }
+
+            particle.pos = rk4(grid, particle.pos, dt);
+            particle.age += dt;
+
+            let wrapped = vector2(particle.pos.x.rem_euclid(cc), particle.pos.y.rem_euclid(cc));
+            if periodic && wrapped != particle.pos {
+                // the trail would otherwise cross the whole domain
+                particle.pos = wrapped;
+                particle.trail.clear();
+            }
+        }
+
+        let lifetime = self.lifetime;
+        let inside = |p: Vector2| (0.0..=cc).contains(&p.x) && (0.0..=cc).contains(&p.y);
+        self.particles.retain(|p| p.age <= lifetime && inside(p.pos));
+
+        for i in 0..self.emitters.len() {
+            let emitter = &mut self.emitters[i];
+            emitter.pending += emitter.rate * dt;
+            let count = emitter.pending.floor();
+            emitter.pending -= count;
+
+            let (center, radius) = (emitter.pos, emitter.radius);
+            for _ in 0..count as usize {
+                if self.particles.len() >= self.max_count {
+                    break;
+                }
+
+                let pos = self.random_in_disk(center, radius);
+                self.add(pos);
+            }
+        }
+    }
+
+    /// everything including the random number generator state, so a restored run continues identically
+    pub fn write(&self, w: &mut Writer) {
+        w.f64(self.lifetime);
+        w.length(self.max_count);
+        w.length(self.trail_length);
+        w.u64(self.rng_state);
+
+        w.length(self.particles.len());
+        for particle in &self.particles {
+            w.vector2(particle.pos);
+            w.f64(particle.age);
+            w.length(particle.trail.len());
+            particle.trail.iter().for_each(|p| w.vector2(*p));
+        }
+
+        w.length(self.emitters.len());
+        for emitter in &self.emitters {
+            w.vector2(emitter.pos);
+            w.f64(emitter.rate);
+            w.f64(emitter.radius);
+            w.f64(emitter.pending);
+        }
+    }
+
+    pub fn read(r: &mut Reader) -> Result<Self, FormatError> {
+        let mut particles = Self {
+            lifetime: r.f64()?,
+            max_count: r.u64()? as usize,
+            trail_length: r.u64()? as usize,
+            rng_state: r.u64()?,
+            ..Self::new()
+        };
+
+        for _ in 0..r.length()? {
+            let pos = r.vector2()?;
+            let age = r.f64()?;
+            let trail = (0..r.length()?).map(|_| r.vector2()).collect::<Result<_, _>>()?;
+            particles.particles.push(Particle { pos, age, trail });
+        }
+
+        for _ in 0..r.length()? {
+            particles.emitters.push(Emitter { pos: r.vector2()?, rate: r.f64()?, radius: r.f64()?, pending: r.f64()? });
+        }
+
+        Ok(particles)
+    }
+}
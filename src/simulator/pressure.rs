@@ -0,0 +1,171 @@
+use super::grid::{StaggeredMACGrid, BoundaryPolicy};
+
+/// outcome of one pressure solve
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProjectionStats {
+    pub iterations: usize,
+    /// largest remaining velocity divergence implied by the pressure residual
+    pub residual: f64
+}
+
+/// successive over-relaxation (red-black Gauss-Seidel) solver for the pressure Poisson equation
+/// with unit density; pressures live at the cell centers, row by row.
+/// walls (all boundary policies except periodic) and obstacles keep their normal velocities
+#[derive(Debug, Clone, PartialEq)]
+pub struct PressureSolver {
+    pub max_iterations: usize,
+    /// stop once the remaining divergence is below this
+    pub tolerance: f64
+}
+
+impl Default for PressureSolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PressureSolver {
+    /// most iterations `validate` accepts
+    pub const MAX_ITERATIONS: usize = 1_000_000;
+
+    pub fn new() -> Self {
+        Self { max_iterations: 500, tolerance: 1e-6 }
+    }
+
+    /// why the settings couldn't be solved with: no iterations, so many that a step never ends,
+    /// or a tolerance that isn't a number of at least 0
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=Self::MAX_ITERATIONS).contains(&self.max_iterations) {
+            return Err(format!("the pressure solver needs between 1 and {} iterations, not {}", Self::MAX_ITERATIONS, self.max_iterations));
+        }
+        if self.tolerance.is_nan() || self.tolerance < 0.0 {
+            return Err(format!("the pressure solver needs a tolerance of at least 0, not {}", self.tolerance));
+        }
+        Ok(())
+    }
+
+    /// divergence of every interior cell, row by row
+    pub fn divergence(grid: &StaggeredMACGrid) -> Vec<f64> {
+        let cc = grid.cell_count;
+        (0..cc).flat_map(|y| (0..cc).map(move |x| (x, y))).map(|(x, y)| Self::divergence_at(grid, x, y)).collect()
+    }
+
+    /// divergence of interior cell (x, y)
+    pub fn divergence_at(grid: &StaggeredMACGrid, x: i32, y: i32) -> f64 {
+        let cc = grid.cell_count;
+        // in periodic domains face cc is the same as face 0
+        let upper = |i: i32| if grid.boundary == BoundaryPolicy::Periodic && i == cc - 1 { 0 } else { i + 1 };
+
+        grid.vel_x_grid(upper(x), y) - grid.vel_x_grid(x, y) + grid.vel_y_grid(x, upper(y)) - grid.vel_y_grid(x, y)
+    }
+
+    /// whether each interior cell (row by row) is not covered by an obstacle
+    fn fluid_mask(grid: &StaggeredMACGrid) -> Vec<bool> {
+        let cc = grid.cell_count;
+        (0..cc * cc).map(|i| !grid.is_solid(i % cc, i / cc)).collect()
+    }
+
+    /// pressure making the velocity field of `grid` divergence free after a step of `dt`,
+    /// warm-started from (and written to) `pressure`
+    pub fn solve(&self, grid: &StaggeredMACGrid, dt: f64, pressure: &mut Vec<f64>) -> ProjectionStats {
+        let n = grid.cell_count as usize;
+        let periodic = grid.boundary == BoundaryPolicy::Periodic;
+
+        if pressure.len() != n * n {
+            *pressure = vec![0.0; n * n];
+        }
+
+        let fluid = &Self::fluid_mask(grid);
+        let fluid_count = fluid.iter().filter(|f| **f).count().max(1);
+
+        let mut rhs: Vec<f64> = Self::divergence(grid).iter().zip(fluid).map(|(d, f)| if *f { d / dt } else { 0.0 }).collect();
+        // closed domains only have a solution if the net inflow vanishes, remove it
+        if !periodic || fluid_count < n * n {
+            let mean = rhs.iter().sum::<f64>() / fluid_count as f64;
+            rhs.iter_mut().zip(fluid).filter(|(_, f)| **f).for_each(|(r, _)| *r -= mean);
+        }
+
+        // fluid neighbours (within the domain) of cell i, walls and obstacles contribute nothing
+        let neighbours = |i: usize| {
+            let (x, y) = (i % n, i / n);
+            let wrap = |v: usize, d: isize| (v as isize + d).rem_euclid(n as isize) as usize;
+            [(-1, 0), (1, 0), (0, -1), (0, 1)].into_iter().filter_map(move |(dx, dy)| {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                let inside = nx >= 0 && ny >= 0 && nx < n as isize && ny < n as isize;
+                (periodic || inside).then(|| wrap(x, dx) + wrap(y, dy) * n).filter(|j| fluid[*j])
+            })
+        };
+
+        // optimal over-relaxation for the model problem
+        let omega = 2.0 / (1.0 + (std::f64::consts::PI / n as f64).sin());
+        let residual = |pressure: &[f64]| {
+            (0..n * n)
+                .filter(|i| fluid[*i])
+                .map(|i| {
+                    let laplacian: f64 = neighbours(i).map(|j| pressure[j] - pressure[i]).sum();
+                    (rhs[i] - laplacian).abs() * dt
+                })
+                .fold(0.0, f64::max)
+        };
+
+        let mut stats = ProjectionStats { iterations: 0, residual: residual(pressure) };
+        while stats.iterations < self.max_iterations && stats.residual > self.tolerance {
+            for parity in 0..2 {
+                for i in (0..n * n).filter(|i| fluid[*i] && (i % n + i / n) % 2 == parity) {
+                    let (sum, count) = neighbours(i).fold((0.0, 0), |(sum, count), j| (sum + pressure[j], count + 1));
+                    if count > 0 {
+                        let gauss_seidel = (sum - rhs[i]) / count as f64;
+                        pressure[i] += omega * (gauss_seidel - pressure[i]);
+                    }
+                }
+            }
+
+            stats.iterations += 1;
+            stats.residual = residual(pressure);
+        }
+
+        // the pressure is only defined up to a constant, obstacles have none
+        let mean = if periodic { 0.0 } else { pressure.iter().zip(fluid).filter(|(_, f)| **f).map(|(p, _)| p).sum::<f64>() / fluid_count as f64 };
+        pressure.iter_mut().zip(fluid).for_each(|(p, f)| *p = if *f { *p - mean } else { 0.0 });
+
+        stats
+    }
+
+    /// solves for the pressure and subtracts its gradient from the velocities of `grid`
+    pub fn project(&self, grid: &mut StaggeredMACGrid, dt: f64, pressure: &mut Vec<f64>) -> ProjectionStats {
+        let stats = self.solve(grid, dt, pressure);
+
+        let cc = grid.cell_count;
+        let periodic = grid.boundary == BoundaryPolicy::Periodic;
+        let p = |x: i32, y: i32| pressure[(x.rem_euclid(cc) + y.rem_euclid(cc) * cc) as usize];
+        // faces on the domain boundary are walls unless the domain is periodic
+        let first = if periodic { 0 } else { 1 };
+        // faces next to obstacles are walls as well
+        let fluid = Self::fluid_mask(grid);
+        let open = |x: i32, y: i32| fluid[(x.rem_euclid(cc) + y.rem_euclid(cc) * cc) as usize];
+
+        for y in 0..cc {
+            for x in first..cc {
+                if open(x, y) && open(x - 1, y) {
+                    *grid.vel_x_grid_mut(x, y) -= dt * (p(x, y) - p(x - 1, y));
+                }
+            }
+        }
+        for x in 0..cc {
+            for y in first..cc {
+                if open(x, y) && open(x, y - 1) {
+                    *grid.vel_y_grid_mut(x, y) -= dt * (p(x, y) - p(x, y - 1));
+                }
+            }
+        }
+
+        if periodic {
+            for i in 0..cc {
+                *grid.vel_x_grid_mut(cc, i) = grid.vel_x_grid(0, i);
+                *grid.vel_y_grid_mut(i, cc) = grid.vel_y_grid(i, 0);
+            }
+        }
+
+        stats
+    }
+}
@@ -0,0 +1,175 @@
+use std::{collections::VecDeque, fmt::Write};
+
+use super::{grid::StaggeredMACGrid, math::Vector2, io::{Writer, Reader, FormatError}};
+
+/// values at a probe after one step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbeSample {
+    pub time: f64,
+    pub velocity: Vector2,
+    pub pressure: f64,
+    pub temperature: f64,
+    pub dye: f64
+}
+
+/// one recorded column of the probe samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeQuantity {
+    VelocityX,
+    VelocityY,
+    Speed,
+    Pressure,
+    Temperature,
+    Dye
+}
+
+impl ProbeQuantity {
+    pub const ALL: [ProbeQuantity; 6] = [Self::VelocityX, Self::VelocityY, Self::Speed, Self::Pressure, Self::Temperature, Self::Dye];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::VelocityX => "x velocity",
+            Self::VelocityY => "y velocity",
+            Self::Speed => "speed",
+            Self::Pressure => "pressure",
+            Self::Temperature => "temperature",
+            Self::Dye => "dye"
+        }
+    }
+
+    pub fn value(&self, sample: &ProbeSample) -> f64 {
+        match self {
+            Self::VelocityX => sample.velocity.x,
+            Self::VelocityY => sample.velocity.y,
+            Self::Speed => sample.velocity.len(),
+            Self::Pressure => sample.pressure,
+            Self::Temperature => sample.temperature,
+            Self::Dye => sample.dye
+        }
+    }
+}
+
+/// named sensor point (world space) recording the interpolated fields every step
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probe {
+    pub name: String,
+    pub pos: Vector2,
+    history: VecDeque<ProbeSample>
+}
+
+impl Probe {
+    pub fn new(name: impl Into<String>, pos: Vector2) -> Self {
+        Self { name: name.into(), pos, history: VecDeque::new() }
+    }
+
+    /// oldest first
+    pub fn history(&self) -> &VecDeque<ProbeSample> {
+        &self.history
+    }
+
+    /// (time, value) pairs of one quantity, oldest first
+    pub fn series(&self, quantity: ProbeQuantity) -> Vec<(f64, f64)> {
+        self.history.iter().map(|s| (s.time, quantity.value(s))).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probes {
+    pub probes: Vec<Probe>,
+    /// number of samples kept per probe
+    pub capacity: usize
+}
+
+impl Default for Probes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Probes {
+    pub fn new() -> Self {
+        Self { probes: Vec::new(), capacity: 10000 }
+    }
+
+    /// adds a probe named after its index
+    pub fn add(&mut self, pos: Vector2) {
+        let name = format!("probe {}", self.probes.len() + 1);
+        self.probes.push(Probe::new(name, pos));
+    }
+
+    pub fn clear_history(&mut self) {
+        for probe in &mut self.probes {
+            probe.history.clear();
+        }
+    }
+
+    /// samples `grid` and the cell-centered `pressure` at every probe
+    pub fn record(&mut self, grid: &StaggeredMACGrid, pressure: &[f64], time: f64) {
+        for probe in &mut self.probes {
+            probe.history.push_back(ProbeSample {
+                time,
+                velocity: grid.vel(probe.pos),
+                pressure: grid.cell_field(pressure, probe.pos),
+                temperature: grid.temp(probe.pos),
+                dye: grid.dye(probe.pos)
+            });
+
+            while probe.history.len() > self.capacity {
+                probe.history.pop_front();
+            }
+        }
+    }
+
+    /// all histories as comma separated values, one line per probe and sample
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("probe,x,y,time,u,v,pressure,temperature,dye\n");
+
+        for probe in &self.probes {
+            // names are quoted so they may contain commas
+            let name = probe.name.replace('"', "\"\"");
+            for s in &probe.history {
+                writeln!(csv, "\"{name}\",{},{},{},{},{},{},{},{}",
+                    probe.pos.x, probe.pos.y, s.time, s.velocity.x, s.velocity.y, s.pressure, s.temperature, s.dye).unwrap();
+            }
+        }
+
+        csv
+    }
+
+    pub fn write(&self, w: &mut Writer) {
+        w.length(self.capacity);
+        w.length(self.probes.len());
+        for probe in &self.probes {
+            w.str(&probe.name);
+            w.vector2(probe.pos);
+            w.length(probe.history.len());
+            for s in &probe.history {
+                w.f64(s.time);
+                w.vector2(s.velocity);
+                w.f64(s.pressure);
+                w.f64(s.temperature);
+                w.f64(s.dye);
+            }
+        }
+    }
+
+    pub fn read(r: &mut Reader) -> Result<Self, FormatError> {
+        let mut probes = Self { capacity: r.u64()? as usize, ..Self::new() };
+
+        for _ in 0..r.length()? {
+            let mut probe = Probe::new(r.str()?, r.vector2()?);
+            for _ in 0..r.length()? {
+                probe.history.push_back(ProbeSample {
+                    time: r.f64()?,
+                    velocity: r.vector2()?,
+                    pressure: r.f64()?,
+                    temperature: r.f64()?,
+                    dye: r.f64()?
+                });
+            }
+            probes.probes.push(probe);
+        }
+
+        Ok(probes)
+    }
+}
@@ -0,0 +1,454 @@
+use std::{fs::{self, File}, path::{Path, PathBuf}};
+
+use serde::{Serialize, Deserialize};
+
+use super::{
+    simulator::Simulator, grid::{StaggeredMACGrid, BoundaryPolicy}, interpolation::Interpolation2DKind, obstacles::Obstacle, pressure::PressureSolver,
+    particles::Emitter, probes::Probe, arrays::GridField, forces::{Forces, Patch}, expression::Expression, math::Vector2, io::FormatError
+};
+
+/// declarative simulation setup, stored as TOML or (for `.json` files) JSON;
+/// positions and lengths are in cells like everywhere else in world space, only expressions
+/// see x and y from 0 to 1 across the domain (`x * n` is in cells)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    pub description: String,
+    pub grid: GridSettings,
+    pub simulation: SimulationSettings,
+    pub initial: InitialFields,
+    /// regions of a field set to a value after the initial fields, in order
+    pub patches: Vec<Patch>,
+    pub obstacles: Vec<Obstacle>,
+    pub forces: Forces,
+    pub emitters: Vec<EmitterSettings>,
+    pub probes: Vec<ProbeSettings>,
+    pub output: OutputSchedule
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GridSettings {
+    pub cells: i32,
+    pub boundary: BoundaryPolicy,
+    pub velocity_interpolation: Interpolation2DKind,
+    pub scalar_interpolation: Interpolation2DKind
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationSettings {
+    pub dt: f64,
+    /// run length for the headless runner, whichever of both ends first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub steps: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<f64>,
+    pub projection: bool,
+    pub solver_iterations: usize,
+    pub solver_tolerance: f64
+}
+
+/// fields left out start at zero
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InitialFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub velocity_x: Option<FieldInit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub velocity_y: Option<FieldInit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<FieldInit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dye: Option<FieldInit>
+}
+
+/// initial values of one field, sampled at its face or cell center positions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged, expecting = "a number, an expression, { value, gradient } or { image, min, max }")]
+pub enum FieldInit {
+    Constant(f64),
+    /// in x and y across the domain from 0 to 1 rather than in cells, see `Expression::parse`
+    Expression(String),
+    Linear(LinearField),
+    Image(ImageField)
+}
+
+/// `value + gradient · position`, with the position in cells
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LinearField {
+    pub value: f64,
+    pub gradient: Vector2
+}
+
+/// PNG stretched over the domain, its luminance mapped from [0, 1] to [min, max];
+/// the first image row is y = 0
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageField {
+    /// relative to the scenario file
+    pub image: PathBuf,
+    #[serde(default)]
+    pub min: f64,
+    #[serde(default = "one")]
+    pub max: f64
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmitterSettings {
+    pub position: Vector2,
+    pub rate: f64,
+    #[serde(default = "one")]
+    pub radius: f64
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProbeSettings {
+    pub name: String,
+    pub position: Vector2
+}
+
+/// where and how often the headless runner writes results; the checkpoint also applies to the GUI;
+/// paths are relative to the scenario file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSchedule {
+    /// steps between outputs, 0 for only the final state
+    pub every: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vtk: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub npz: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_every: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<PathBuf>
+}
+
+/// ready-made setups shipped with flowy, see `scenarios/`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinScenario {
+    LidDrivenCavity,
+    KarmanVortexStreet,
+    SmokePlume,
+    KelvinHelmholtz
+}
+
+impl BuiltinScenario {
+    pub const ALL: [BuiltinScenario; 4] = [Self::LidDrivenCavity, Self::KarmanVortexStreet, Self::SmokePlume, Self::KelvinHelmholtz];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::LidDrivenCavity => "lid-driven cavity",
+            Self::KarmanVortexStreet => "Kármán vortex street",
+            Self::SmokePlume => "smoke plume",
+            Self::KelvinHelmholtz => "Kelvin-Helmholtz instability"
+        }
+    }
+
+    /// name on the command line
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::LidDrivenCavity => "cavity",
+            Self::KarmanVortexStreet => "karman",
+            Self::SmokePlume => "plume",
+            Self::KelvinHelmholtz => "kelvin-helmholtz"
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.key() == key)
+    }
+
+    pub fn scenario(&self) -> Scenario {
+        let text = match self {
+            Self::LidDrivenCavity => include_str!("../../scenarios/lid_driven_cavity.toml"),
+            Self::KarmanVortexStreet => include_str!("../../scenarios/karman_vortex_street.toml"),
+            Self::SmokePlume => include_str!("../../scenarios/smoke_plume.toml"),
+            Self::KelvinHelmholtz => include_str!("../../scenarios/kelvin_helmholtz.toml")
+        };
+        Scenario::from_toml(text).expect("built-in scenarios are valid")
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            name: String::from("untitled"),
+            description: String::new(),
+            grid: GridSettings::default(),
+            simulation: SimulationSettings::default(),
+            initial: InitialFields::default(),
+            patches: Vec::new(),
+            obstacles: Vec::new(),
+            forces: Forces::new(),
+            emitters: Vec::new(),
+            probes: Vec::new(),
+            output: OutputSchedule::default()
+        }
+    }
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        let grid = StaggeredMACGrid::new(20);
+        Self {
+            cells: grid.cell_count,
+            boundary: grid.boundary,
+            velocity_interpolation: grid.vel_interpolation,
+            scalar_interpolation: grid.temp_interpolation
+        }
+    }
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        Self { dt: 0.2, steps: None, until: None, projection: false, solver_iterations: 500, solver_tolerance: 1e-6 }
+    }
+}
+
+impl Default for OutputSchedule {
+    fn default() -> Self {
+        Self { every: 0, vtk: None, npz: None, diagnostics: None, checkpoint: None, checkpoint_every: 100, record: None }
+    }
+}
+
+impl Scenario {
+    pub fn from_toml(text: &str) -> Result<Self, FormatError> {
+        let scenario: Self = toml::from_str(text).map_err(|e| FormatError::Invalid(e.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, FormatError> {
+        let scenario: Self = serde_json::from_str(text).map_err(|e| FormatError::Invalid(e.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("scenarios are representable in TOML")
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scenarios are representable in JSON")
+    }
+
+    /// reads a `.json` or otherwise TOML scenario; relative image and output paths are resolved against its directory
+    pub fn load(path: &Path) -> Result<Self, FormatError> {
+        let text = fs::read_to_string(path)?;
+        let mut scenario = if is_json(path) { Self::from_json(&text)? } else { Self::from_toml(&text)? };
+
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let initial = &mut scenario.initial;
+        for init in [&mut initial.velocity_x, &mut initial.velocity_y, &mut initial.temperature, &mut initial.dye].into_iter().flatten() {
+            if let FieldInit::Image(field) = init {
+                field.image = directory.join(&field.image);
+            }
+        }
+        let output = &mut scenario.output;
+        for path in [&mut output.vtk, &mut output.npz, &mut output.diagnostics, &mut output.checkpoint, &mut output.record].into_iter().flatten() {
+            *path = directory.join(&*path);
+        }
+        Ok(scenario)
+    }
+
+    /// a built-in scenario by key, otherwise a scenario file
+    pub fn open(name_or_path: &str) -> Result<Self, FormatError> {
+        match BuiltinScenario::from_key(name_or_path) {
+            Some(builtin) => Ok(builtin.scenario()),
+            None => Self::load(Path::new(name_or_path))
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), FormatError> {
+        fs::write(path, if is_json(path) { self.to_json() } else { self.to_toml() })?;
+        Ok(())
+    }
+
+    /// checks the values serde can't, so `build` only fails on unreadable images and non-finite expressions
+    pub fn validate(&self) -> Result<(), FormatError> {
+        let invalid = |reason: String| Err(FormatError::Invalid(reason));
+
+        if !(1..=1 << 14).contains(&self.grid.cells) {
+            return invalid(format!("grid.cells must be between 1 and 16384, not {}", self.grid.cells));
+        }
+        let initial = &self.initial;
+        for (name, init) in [("velocity_x", &initial.velocity_x), ("velocity_y", &initial.velocity_y), ("temperature", &initial.temperature), ("dye", &initial.dye)] {
+            if let Some(FieldInit::Expression(source)) = init {
+                if let Err(e) = Expression::parse(source) {
+                    return invalid(format!("initial.{name}: {e} of {source:?}"));
+                }
+            }
+        }
+        let simulation = &self.simulation;
+        if !(simulation.dt > 0.0 && simulation.dt.is_finite()) {
+            return invalid(format!("simulation.dt must be positive, not {}", simulation.dt));
+        }
+        if simulation.until.is_some_and(|t| !t.is_finite()) {
+            return invalid(String::from("simulation.until must be finite"));
+        }
+        let solver = PressureSolver { max_iterations: simulation.solver_iterations, tolerance: simulation.solver_tolerance };
+        if let Err(e) = solver.validate() {
+            return invalid(format!("simulation: {e}"));
+        }
+
+        let regions = self.patches.iter().chain(&self.forces.sources).map(|p| &p.region);
+        for (i, shape) in self.obstacles.iter().chain(regions).enumerate() {
+            let valid = match shape {
+                Obstacle::Rectangle { min, max } => min.x <= max.x && min.y <= max.y,
+                Obstacle::Circle { radius, .. } => *radius > 0.0,
+                Obstacle::Polygon { points } => points.len() >= 3
+            };
+            if !valid {
+                let (obstacles, patches) = (self.obstacles.len(), self.patches.len());
+                let what = match i {
+                    i if i < obstacles => format!("obstacles[{i}]"),
+                    i if i < obstacles + patches => format!("patches[{}].region", i - obstacles),
+                    i => format!("forces.sources[{}].region", i - obstacles - patches)
+                };
+                return invalid(format!("{what} is empty (min > max, radius <= 0 or fewer than 3 points)"));
+            }
+        }
+        for (i, emitter) in self.emitters.iter().enumerate() {
+            if !(emitter.rate >= 0.0 && emitter.radius >= 0.0) {
+                return invalid(format!("emitters[{i}] needs a non-negative rate and radius"));
+            }
+        }
+        if self.output.checkpoint.is_some() && self.output.checkpoint_every == 0 {
+            return invalid(String::from("output.checkpoint_every must be positive"));
+        }
+        Ok(())
+    }
+
+    /// a fresh simulator at time 0 set up as described
+    pub fn build(&self) -> Result<Simulator, FormatError> {
+        self.validate()?;
+
+        let mut grid = StaggeredMACGrid::new(self.grid.cells);
+        grid.boundary = self.grid.boundary;
+        grid.vel_interpolation = self.grid.velocity_interpolation;
+        grid.temp_interpolation = self.grid.scalar_interpolation;
+        grid.obstacles = self.obstacles.clone();
+
+        let initial = &self.initial;
+        for (field, init) in GridField::ALL.into_iter().zip([&initial.velocity_x, &initial.velocity_y, &initial.temperature, &initial.dye]) {
+            if let Some(init) = init {
+                init.apply(&mut grid, field)?;
+            }
+        }
+        for patch in &self.patches {
+            patch.apply(&mut grid);
+        }
+        grid.update_obstacles();
+
+        let mut simulator = Simulator::new(grid);
+        simulator.forces = self.forces.clone();
+        simulator.projection = self.simulation.projection;
+        simulator.pressure_solver.max_iterations = self.simulation.solver_iterations;
+        simulator.pressure_solver.tolerance = self.simulation.solver_tolerance;
+        simulator.particles.emitters = self.emitters.iter().map(|e| Emitter::new(e.position, e.rate, e.radius)).collect();
+        simulator.probes.probes = self.probes.iter().map(|p| Probe::new(p.name.clone(), p.position)).collect();
+        if let Some(path) = &self.output.checkpoint {
+            simulator.checkpoints.path = path.clone();
+            simulator.checkpoints.interval = self.output.checkpoint_every;
+        }
+
+        Ok(simulator)
+    }
+
+    /// the setup of a running simulation: grid settings, solver, obstacles, forces, emitters, probes
+    /// and the checkpoint schedule; the field values themselves are left out, snapshots keep those
+    pub fn from_simulator(simulator: &Simulator, dt: f64) -> Self {
+        let grid = &simulator.grid;
+        let checkpoints = &simulator.checkpoints;
+
+        Self {
+            grid: GridSettings {
+                cells: grid.cell_count,
+                boundary: grid.boundary,
+                velocity_interpolation: grid.vel_interpolation,
+                scalar_interpolation: grid.temp_interpolation
+            },
+            simulation: SimulationSettings {
+                dt,
+                steps: None,
+                until: None,
+                projection: simulator.projection,
+                solver_iterations: simulator.pressure_solver.max_iterations,
+                solver_tolerance: simulator.pressure_solver.tolerance
+            },
+            obstacles: grid.obstacles.clone(),
+            forces: simulator.forces.clone(),
+            emitters: simulator.particles.emitters.iter().map(|e| EmitterSettings { position: e.pos, rate: e.rate, radius: e.radius }).collect(),
+            probes: simulator.probes.probes.iter().map(|p| ProbeSettings { name: p.name.clone(), position: p.pos }).collect(),
+            output: OutputSchedule {
+                // absolute, since `load` resolves relative paths against wherever the scenario is saved
+                checkpoint: (checkpoints.interval > 0).then(|| std::path::absolute(&checkpoints.path).unwrap_or_else(|_| checkpoints.path.clone())),
+                checkpoint_every: if checkpoints.interval > 0 { checkpoints.interval } else { OutputSchedule::default().checkpoint_every },
+                ..OutputSchedule::default()
+            },
+            ..Self::default()
+        }
+    }
+}
+
+impl FieldInit {
+    fn apply(&self, grid: &mut StaggeredMACGrid, field: GridField) -> Result<(), FormatError> {
+        match self {
+            Self::Constant(value) => field.map(grid, |_, _| *value),
+            Self::Expression(source) => {
+                let expression = Expression::parse(source).map_err(|e| FormatError::Invalid(format!("{source:?}: {e}")))?;
+                expression.fill(grid, field).map_err(|e| FormatError::Invalid(format!("{source:?}: {e}")))?;
+            },
+            Self::Linear(LinearField { value, gradient }) => field.map(grid, |p, _| value + gradient.x * p.x + gradient.y * p.y),
+            Self::Image(ImageField { image, min, max }) => {
+                let (width, height, luminance) = read_luminance(image)?;
+                let cc = grid.cell_count as f64;
+                field.map(grid, |p, _| {
+                    let x = ((p.x / cc * width as f64) as usize).min(width - 1);
+                    let y = ((p.y / cc * height as f64) as usize).min(height - 1);
+                    min + luminance[x + y * width] * (max - min)
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// (width, height, row-major luminance in [0, 1]) of a PNG
+fn read_luminance(path: &Path) -> Result<(usize, usize, Vec<f64>), FormatError> {
+    let image_error = |e: png::DecodingError| FormatError::Invalid(format!("{}: {e}", path.display()));
+
+    let mut decoder = png::Decoder::new(File::open(path)?);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(image_error)?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(image_error)?;
+
+    let channels = info.color_type.samples();
+    let luminance = buf[..info.buffer_size()].chunks_exact(channels).map(|px| {
+        let value = match px.len() {
+            1 | 2 => px[0] as f64,
+            _ => 0.2126 * px[0] as f64 + 0.7152 * px[1] as f64 + 0.0722 * px[2] as f64
+        };
+        value / 255.0
+    }).collect();
+
+    Ok((info.width as usize, info.height as usize, luminance))
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "json")
+}
@@ -1,12 +1,31 @@
+use std::time::Instant;
+
 use chrono::{NaiveTime, Local};
 
-use super::{grid::StaggeredMACGrid, math::{Vector2, vector2}};
+use super::{grid::StaggeredMACGrid, math::{Vector2, vector2}, particles::Particles, pressure::{PressureSolver, ProjectionStats}, diagnostics::{Diagnostics, DiagnosticsHistory}, probes::Probes, checkpoint::CheckpointSchedule, forces::Forces};
 
 
 pub struct Simulator
 {
     pub grid: StaggeredMACGrid,
+    pub particles: Particles,
+    pub forces: Forces,
+
+    // pressure projection after advection, off by default; the pressure of the last solve is kept as a warm start
+    pub projection: bool,
+    pub pressure_solver: PressureSolver,
+    pub pressure: Vec<f64>,
+    pub projection_stats: ProjectionStats,
+
+    // measured after every step
+    pub diagnostics: DiagnosticsHistory,
+    pub probes: Probes,
+
+    pub checkpoints: CheckpointSchedule,
+
     pub current_time_step: u32,
+    /// simulation time, the sum of all advected time steps
+    pub time: f64,
     pub last_stepped: NaiveTime
 }
 
@@ -14,49 +33,148 @@ impl Simulator {
     pub fn new(grid: StaggeredMACGrid) -> Self {
         Self {
             grid,
+            particles: Particles::new(),
+            forces: Forces::new(),
+            projection: false,
+            pressure_solver: PressureSolver::new(),
+            pressure: Vec::new(),
+            projection_stats: ProjectionStats::default(),
+            diagnostics: DiagnosticsHistory::new(1000),
+            probes: Probes::new(),
+            checkpoints: CheckpointSchedule::new(),
             current_time_step: 0,
+            time: 0.0,
             last_stepped: Local::now().time()
         }
     }
 
+    /// the 20-cell setup the GUI starts with: a hot cell in a diagonal shear flow
+    pub fn demo() -> Self {
+        let mut grid = StaggeredMACGrid::new(20);
+        *grid.temp_grid_mut(2, 5) = 10.0;
+
+        let tcc = grid.velocities_x.len();
+
+        for (i, vx) in grid.velocities_x.iter_mut().enumerate() {
+            *vx = i as f64 / tcc as f64;
+        }
+
+        for (i, vy) in grid.velocities_y.iter_mut().enumerate() {
+            *vy = i as f64 / tcc as f64;
+        }
+
+        for col in -1..=grid.cell_count {
+            *grid.vel_x_grid_mut(col, -1) = 0.0;
+            *grid.vel_x_grid_mut(col, grid.cell_count) = 0.0;
+            *grid.vel_y_grid_mut(-1, col) = 0.0;
+            *grid.vel_y_grid_mut(grid.cell_count, col) = 0.0;
+        }
+
+        for row in -1..=grid.cell_count {
+            *grid.vel_x_grid_mut(-1, row) = 0.0;
+            *grid.vel_x_grid_mut(grid.cell_count + 1, row) = 0.0;
+            *grid.vel_y_grid_mut(row, -1) = 0.0;
+            *grid.vel_y_grid_mut(row, grid.cell_count + 1) = 0.0;
+        }
+
+        Self::new(grid)
+    }
+
+    /// applies the forces, advects all fields and, if enabled, makes the velocity field divergence free again
+    pub fn step(&mut self, dt: f64) {
+        let start = Instant::now();
+
+        self.forces.apply(&mut self.grid, dt);
+        self.advect(dt);
+
+        if self.projection {
+            self.projection_stats = self.pressure_solver.project(&mut self.grid, dt, &mut self.pressure);
+        }
+
+        let step_time = start.elapsed().as_secs_f64() * 1000.0;
+        self.diagnostics.push(Diagnostics::measure(self, dt, step_time));
+        self.probes.record(&self.grid, &self.pressure, self.time);
+
+        if self.checkpoints.is_due(self.current_time_step) {
+            let path = self.checkpoints.path.clone();
+            let step = self.current_time_step;
+            self.checkpoints.last = Some(self.save_checkpoint(&path, dt).map(|_| step).map_err(|e| e.to_string()));
+        }
+    }
+
     pub fn advect(&mut self, dt: f64) {
         let cc = self.grid.cell_count;
         let mut grid_new = self.grid.clone();
 
+        // back-traced positions and sampled values of one row, reused across rows
+        let mut positions = Vec::with_capacity(cc as usize + 1);
+        let mut velocities = vec![vector2(0.0, 0.0); cc as usize + 1];
+        let mut temperatures = vec![0.0; cc as usize];
+
         // advect velocities
         for row in 0..cc {
-            for col in 0..=cc {
-                // x velocities
+            // x velocities
+            positions.clear();
+            positions.extend((0..=cc).map(|col| {
                 let xp = vector2(col as f64, row as f64 + 0.5);
-                let xg = self.trace_back(dt, xp).clamp(-1.0, (cc + 2) as f64);
+                self.trace_back(dt, xp)
+            }));
 
-                let v_new = self.grid.vel(xg);
-                *grid_new.vel_x_grid_mut(col, row) = v_new.x;
+            self.grid.vel_batch(&positions, &mut velocities);
+            for (col, v_new) in velocities.iter().enumerate() {
+                *grid_new.vel_x_grid_mut(col as i32, row) = v_new.x;
+            }
 
-                // y velocities
+            // y velocities
+            positions.clear();
+            positions.extend((0..=cc).map(|col| {
                 let xp = vector2(row as f64 + 0.5, col as f64);
-                let xg = self.trace_back(dt, xp).clamp(-1.0, (cc + 2) as f64);
+                self.trace_back(dt, xp)
+            }));
 
-                let v_new = self.grid.vel(xg);
-                *grid_new.vel_y_grid_mut(row, col) = v_new.y;
+            self.grid.vel_batch(&positions, &mut velocities);
+            for (col, v_new) in velocities.iter().enumerate() {
+                *grid_new.vel_y_grid_mut(row, col as i32) = v_new.y;
             }
         }
 
         // advect temperature
         for y in 0..cc {
-            for x in 0..cc {
+            positions.clear();
+            positions.extend((0..cc).map(|x| {
+                let xp = vector2(x as f64 + 0.5, y as f64 + 0.5);
+                self.trace_back(dt, xp)
+            }));
+
+            self.grid.temp_batch(&positions, &mut temperatures);
+            for (x, temp_new) in temperatures.iter().enumerate() {
+                *grid_new.temp_grid_mut(x as i32, y) = *temp_new;
+            }
+        }
+
+        // advect dye
+        for y in 0..cc {
+            positions.clear();
+            positions.extend((0..cc).map(|x| {
                 let xp = vector2(x as f64 + 0.5, y as f64 + 0.5);
-                let xg = self.trace_back(dt, xp).clamp(-1.0, (cc + 2) as f64);
+                self.trace_back(dt, xp)
+            }));
 
-                let temp_new = self.grid.temp(xg);
-                *grid_new.temp_grid_mut(x, y) = temp_new;
+            self.grid.dye_batch(&positions, &mut temperatures);
+            for (x, dye_new) in temperatures.iter().enumerate() {
+                *grid_new.dye_grid_mut(x as i32, y) = *dye_new;
             }
         }
 
+        // tracers move through the field of this step
+        self.particles.step(&self.grid, dt);
+
         self.grid = grid_new;
+        self.grid.enforce_obstacles();
 
         self.last_stepped = Local::now().time();
         self.current_time_step += 1;
+        self.time += dt;
     }
 
     fn trace_back(&self, dt: f64, pos: Vector2) -> Vector2 {
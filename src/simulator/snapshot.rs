@@ -0,0 +1,116 @@
+use std::path::Path;
+
+use super::{grid::StaggeredMACGrid, simulator::Simulator, pressure::PressureSolver, forces::Forces, io::{Writer, Reader, FormatError}};
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"FLOWYSNP";
+const COLLECTION_MAGIC: &[u8; 8] = b"FLOWYSNC";
+/// version 2 added the forces
+const VERSION: u32 = 2;
+
+/// simulation state and settings at one time step
+#[derive(Clone, PartialEq)]
+pub struct Snapshot {
+    pub timestep: u32,
+    pub time: f64,
+    pub dt: f64,
+    pub projection: bool,
+    pub pressure_solver: PressureSolver,
+    pub grid: StaggeredMACGrid,
+    pub forces: Forces
+}
+
+impl Snapshot {
+    pub fn new(simulator: &Simulator, dt: f64) -> Self {
+        Self {
+            timestep: simulator.current_time_step,
+            time: simulator.time,
+            dt,
+            projection: simulator.projection,
+            pressure_solver: simulator.pressure_solver.clone(),
+            grid: simulator.grid.clone(),
+            forces: simulator.forces.clone()
+        }
+    }
+
+    /// puts the grid, the forces, the step count and the solver settings back into `simulator`
+    pub fn apply(&self, simulator: &mut Simulator) {
+        simulator.grid = self.grid.clone();
+        simulator.forces = self.forces.clone();
+        simulator.current_time_step = self.timestep;
+        simulator.time = self.time;
+        simulator.projection = self.projection;
+        simulator.pressure_solver = self.pressure_solver.clone();
+    }
+
+    fn write(&self, w: &mut Writer) {
+        w.u32(self.timestep);
+        w.f64(self.time);
+        w.f64(self.dt);
+        w.bool(self.projection);
+        w.length(self.pressure_solver.max_iterations);
+        w.f64(self.pressure_solver.tolerance);
+        self.grid.write(w);
+        self.forces.write(w);
+    }
+
+    /// rejects settings the GUI and the runner couldn't step with, like `Scenario::validate`;
+    /// snapshots before version 2 have no forces
+    fn read(r: &mut Reader, version: u32) -> Result<Self, FormatError> {
+        let (timestep, time, dt, projection) = (r.u32()?, r.f64()?, r.f64()?, r.bool()?);
+        let pressure_solver = PressureSolver { max_iterations: r.u64()? as usize, tolerance: r.f64()? };
+
+        if !(time.is_finite() && dt > 0.0 && dt.is_finite()) {
+            return Err(FormatError::Invalid(format!("time {time} and time step {dt} must be finite and the time step positive")));
+        }
+        pressure_solver.validate().map_err(FormatError::Invalid)?;
+
+        let grid = StaggeredMACGrid::read(r)?;
+        let forces = if version >= 2 { Forces::read(r)? } else { Forces::new() };
+        Ok(Self { timestep, time, dt, projection, pressure_solver, grid, forces })
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, FormatError> {
+        let (mut r, version) = Reader::new(data, SNAPSHOT_MAGIC, "snapshot")?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+
+        let snapshot = Self::read(&mut r, version)?;
+        r.finish()?;
+        Ok(snapshot)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), FormatError> {
+        let mut w = Writer::new(SNAPSHOT_MAGIC, VERSION);
+        self.write(&mut w);
+        Ok(w.save(path)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, FormatError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+pub fn collection_from_bytes(data: &[u8]) -> Result<Vec<Snapshot>, FormatError> {
+    let (mut r, version) = Reader::new(data, COLLECTION_MAGIC, "snapshot collection")?;
+    if !(1..=VERSION).contains(&version) {
+        return Err(FormatError::UnsupportedVersion(version));
+    }
+
+    let len = r.length()?;
+    let snapshots = (0..len).map(|_| Snapshot::read(&mut r, version)).collect::<Result<_, _>>()?;
+    r.finish()?;
+    Ok(snapshots)
+}
+
+/// all snapshots in one file
+pub fn save_collection(snapshots: &[Snapshot], path: &Path) -> Result<(), FormatError> {
+    let mut w = Writer::new(COLLECTION_MAGIC, VERSION);
+    w.length(snapshots.len());
+    snapshots.iter().for_each(|s| s.write(&mut w));
+    Ok(w.save(path)?)
+}
+
+pub fn load_collection(path: &Path) -> Result<Vec<Snapshot>, FormatError> {
+    collection_from_bytes(&std::fs::read(path)?)
+}
@@ -0,0 +1,167 @@
+use std::{fmt::Write, fs, io, path::{Path, PathBuf}};
+
+use super::{grid::StaggeredMACGrid, derived::DerivedField, math::{vector2, Vector2}};
+
+/// values of one data section (cells or points), rows of `width` values
+struct Section {
+    width: usize,
+    velocity: Vec<Vector2>,
+    scalars: Vec<(String, Vec<f64>)>
+}
+
+/// cell data: the cell-centered velocity and every scalar as stored or derived;
+/// point data: everything interpolated to the cell corners
+fn sections(grid: &StaggeredMACGrid, pressure: &[f64]) -> (Section, Section) {
+    let cc = grid.cell_count;
+
+    let mut cell_scalars = vec![
+        (String::from("temperature"), grid.cell_temperatures()),
+        (String::from("dye"), grid.cell_dye()),
+        (String::from("solid"), (0..cc * cc).map(|i| grid.is_solid(i % cc, i / cc) as u8 as f64).collect())
+    ];
+    for field in DerivedField::ALL {
+        cell_scalars.push((field.name().to_lowercase().replace(['-', ' '], "_"), field.compute(grid, pressure)));
+    }
+
+    let corners: Vec<Vector2> = (0..=cc).flat_map(|y| (0..=cc).map(move |x| vector2(x as f64, y as f64))).collect();
+    let point_velocity: Vec<Vector2> = corners.iter().map(|p| grid.vel(*p)).collect();
+    let point_scalars = vec![
+        (String::from("temperature"), corners.iter().map(|p| grid.temp(*p)).collect()),
+        (String::from("dye"), corners.iter().map(|p| grid.dye(*p)).collect()),
+        (String::from("pressure"), corners.iter().map(|p| grid.cell_field(pressure, *p)).collect()),
+        (String::from("speed"), point_velocity.iter().map(|v| v.len()).collect())
+    ];
+
+    (
+        Section { width: cc as usize, velocity: grid.cell_centered_velocities(), scalars: cell_scalars },
+        Section { width: cc as usize + 1, velocity: point_velocity, scalars: point_scalars }
+    )
+}
+
+/// shortest round-tripping scientific notation, so tiny values don't expand to hundreds of digits;
+/// ParaView's ASCII readers reject nan and inf, so NaN is written as 0 and infinities as the largest finite value
+fn number(v: f64) -> String {
+    let v = if v.is_nan() { 0.0 } else { v.clamp(f64::MIN, f64::MAX) };
+    format!("{v:e}")
+}
+
+fn write_vectors(out: &mut String, section: &Section) {
+    for row in section.velocity.chunks(section.width) {
+        let line: Vec<String> = row.iter().map(|v| format!("{} {} 0", number(v.x), number(v.y))).collect();
+        writeln!(out, "{}", line.join(" ")).unwrap();
+    }
+}
+
+fn write_scalars(out: &mut String, values: &[f64], width: usize) {
+    for row in values.chunks(width) {
+        let line: Vec<String> = row.iter().map(|v| number(*v)).collect();
+        writeln!(out, "{}", line.join(" ")).unwrap();
+    }
+}
+
+/// legacy VTK (structured points, ASCII) with one unit per cell and the simulation time as field data
+pub fn legacy(grid: &StaggeredMACGrid, pressure: &[f64], time: f64) -> String {
+    let cc = grid.cell_count;
+    let (cells, points) = sections(grid, pressure);
+
+    let mut out = String::from("# vtk DataFile Version 3.0\n");
+    writeln!(out, "flowy grid at t = {time}").unwrap();
+    out.push_str("ASCII\nDATASET STRUCTURED_POINTS\n");
+    writeln!(out, "DIMENSIONS {} {} 1", cc + 1, cc + 1).unwrap();
+    out.push_str("ORIGIN 0 0 0\nSPACING 1 1 1\n");
+    writeln!(out, "FIELD FieldData 1\nTIME 1 1 double\n{time}").unwrap();
+
+    for (header, section) in [(format!("CELL_DATA {}", cc * cc), &cells), (format!("POINT_DATA {}", (cc + 1) * (cc + 1)), &points)] {
+        writeln!(out, "{header}\nVECTORS velocity double").unwrap();
+        write_vectors(&mut out, section);
+        for (name, values) in &section.scalars {
+            writeln!(out, "SCALARS {name} double 1\nLOOKUP_TABLE default").unwrap();
+            write_scalars(&mut out, values, section.width);
+        }
+    }
+
+    out
+}
+
+/// VTK XML image data (`.vti`, ASCII)
+pub fn image_data(grid: &StaggeredMACGrid, pressure: &[f64], time: f64) -> String {
+    let cc = grid.cell_count;
+    let (cells, points) = sections(grid, pressure);
+
+    let mut out = String::from("<?xml version=\"1.0\"?>\n");
+    out.push_str("<VTKFile type=\"ImageData\" version=\"1.0\" byte_order=\"LittleEndian\">\n");
+    writeln!(out, "  <ImageData WholeExtent=\"0 {cc} 0 {cc} 0 0\" Origin=\"0 0 0\" Spacing=\"1 1 1\">").unwrap();
+    writeln!(out, "    <FieldData>\n      <DataArray type=\"Float64\" Name=\"TimeValue\" NumberOfTuples=\"1\" format=\"ascii\">{time}</DataArray>\n    </FieldData>").unwrap();
+    writeln!(out, "    <Piece Extent=\"0 {cc} 0 {cc} 0 0\">").unwrap();
+
+    for (tag, section) in [("PointData", &points), ("CellData", &cells)] {
+        writeln!(out, "      <{tag} Vectors=\"velocity\" Scalars=\"temperature\">").unwrap();
+        out.push_str("        <DataArray type=\"Float64\" Name=\"velocity\" NumberOfComponents=\"3\" format=\"ascii\">\n");
+        write_vectors(&mut out, section);
+        out.push_str("        </DataArray>\n");
+        for (name, values) in &section.scalars {
+            writeln!(out, "        <DataArray type=\"Float64\" Name=\"{name}\" format=\"ascii\">").unwrap();
+            write_scalars(&mut out, values, section.width);
+            out.push_str("        </DataArray>\n");
+        }
+        writeln!(out, "      </{tag}>").unwrap();
+    }
+
+    out.push_str("    </Piece>\n  </ImageData>\n</VTKFile>\n");
+    out
+}
+
+/// ParaView collection (`.pvd`) of (time, file) pairs, files relative to the collection
+pub fn collection(entries: &[(f64, String)]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\"?>\n");
+    out.push_str("<VTKFile type=\"Collection\" version=\"0.1\" byte_order=\"LittleEndian\">\n  <Collection>\n");
+    for (time, file) in entries {
+        let file = file.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;").replace('>', "&gt;");
+        writeln!(out, "    <DataSet timestep=\"{time}\" group=\"\" part=\"0\" file=\"{file}\"/>").unwrap();
+    }
+    out.push_str("  </Collection>\n</VTKFile>\n");
+    out
+}
+
+/// writes `.vti` files of consecutive steps into a directory and keeps a `.pvd` collection of them up to date
+#[derive(Debug, Clone, PartialEq)]
+pub struct VtkSeries {
+    pub directory: PathBuf,
+    pub name: String,
+    entries: Vec<(f64, String)>
+}
+
+impl VtkSeries {
+    pub fn new(directory: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+        Self { directory: directory.into(), name: name.into(), entries: Vec::new() }
+    }
+
+    /// (time, file name) of every step written so far
+    pub fn entries(&self) -> &[(f64, String)] {
+        &self.entries
+    }
+
+    pub fn collection_path(&self) -> PathBuf {
+        self.directory.join(format!("{}.pvd", self.name))
+    }
+
+    pub fn write_step(&mut self, grid: &StaggeredMACGrid, pressure: &[f64], time: f64, time_step: u32) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+
+        let file = format!("{}_{time_step:06}.vti", self.name);
+        fs::write(self.directory.join(&file), image_data(grid, pressure, time))?;
+        self.entries.push((time, file));
+
+        fs::write(self.collection_path(), collection(&self.entries))
+    }
+}
+
+/// `.vti` paths get XML image data, everything else legacy VTK
+pub fn export(grid: &StaggeredMACGrid, pressure: &[f64], time: f64, path: &Path) -> io::Result<()> {
+    let contents = if path.extension().is_some_and(|e| e == "vti") {
+        image_data(grid, pressure, time)
+    } else {
+        legacy(grid, pressure, time)
+    };
+    fs::write(path, contents)
+}
@@ -1,4 +1,8 @@
-use crate::simulator::{grid::StaggeredMACGrid, interpolation::{LinearInterpolation, Interpolation, CubicInterpolation}};
+use crate::simulator::{grid::{StaggeredMACGrid, BoundaryPolicy}, interpolation::{LinearInterpolation, Interpolation, CubicInterpolation, Interpolation2DKind, MonotoneCubicInterpolation, ClampedCubicInterpolation, Weno4Interpolation, Weno6Interpolation, CubicBSplineInterpolation, CatmullRomInterpolation}, math::{vector2, Vector2}, simulator::Simulator, integration::rk4, particles::{Particles, Emitter}, pressure::PressureSolver, derived::DerivedField, brush::Brush, obstacles::Obstacle, diagnostics::DiagnosticSeries, probes::ProbeQuantity, io::FormatError, vtk::{self, VtkSeries}, arrays::{self, GridField}, snapshot::{self, Snapshot}};
+use crate::visualize::{colormap::{ColorScale, Colormap, Normalization, Gradient}, flow_lines::FlowLines, lic::Lic, render::LayerStack, recording::{self, Recorder, RecordingFormat}};
+use crate::simulator::{scenario::{Scenario, FieldInit, BuiltinScenario}, forces::{Forces, Patch}, expression::Expression};
+use crate::headless::{self, RunOptions, RunOutcome};
+use epaint::Color32;
 
 #[test]
 fn grid_vel_x() {
@@ -96,3 +100,1458 @@ fn cubic_interpolate() {
     assert!(CubicInterpolation::interpolate(&values, 1.3) >= 4.0);
     assert!(CubicInterpolation::interpolate(&values, 1.3) <= 16.0);
 }
+
+/// deterministic pseudo-random numbers in [0, 1) for the batched tests
+fn lcg(state: &mut u64) -> f64 {
+    *state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (*state >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[test]
+fn cubic_interpolate_batch() {
+    let mut state = 1;
+    let values: Vec<f64> = (0..23).map(|_| lcg(&mut state) * 10.0 - 5.0).collect();
+
+    // odd length to exercise the scalar remainder
+    let indices: Vec<f64> = (0..1001).map(|_| lcg(&mut state) * (values.len() - 1) as f64).collect();
+    let mut batched = vec![0.0; indices.len()];
+    CubicInterpolation::interpolate_batch(&values, &indices, &mut batched);
+
+    for (index, b) in indices.iter().zip(&batched) {
+        let scalar = CubicInterpolation::interpolate(&values, *index);
+        assert!((scalar - b).abs() <= 1e-12 * scalar.abs().max(1.0));
+    }
+}
+
+#[test]
+fn grid_sample_batch() {
+    let cc = 16;
+    let mut state = 7;
+
+    let mut grid = StaggeredMACGrid::new(cc);
+    grid.velocities_x.iter_mut().for_each(|v| *v = lcg(&mut state) - 0.5);
+    grid.velocities_y.iter_mut().for_each(|v| *v = lcg(&mut state) - 0.5);
+    grid.temperature.iter_mut().for_each(|t| *t = lcg(&mut state));
+
+    let positions: Vec<Vector2> = (0..500)
+        .map(|_| vector2(lcg(&mut state) * cc as f64, lcg(&mut state) * cc as f64))
+        .collect();
+
+    let mut vels = vec![vector2(0.0, 0.0); positions.len()];
+    let mut temps = vec![0.0; positions.len()];
+    grid.vel_batch(&positions, &mut vels);
+    grid.temp_batch(&positions, &mut temps);
+
+    for (i, pos) in positions.iter().enumerate() {
+        let v = grid.vel(*pos);
+        assert!((v.x - vels[i].x).abs() <= 1e-12);
+        assert!((v.y - vels[i].y).abs() <= 1e-12);
+        assert!((grid.temp(*pos) - temps[i]).abs() <= 1e-12);
+    }
+}
+
+#[test]
+fn advect_matches_scalar() {
+    let cc = 12;
+    let dt = 0.3;
+    let mut state = 3;
+
+    let mut grid = StaggeredMACGrid::new(cc);
+    grid.velocities_x.iter_mut().for_each(|v| *v = lcg(&mut state) - 0.5);
+    grid.velocities_y.iter_mut().for_each(|v| *v = lcg(&mut state) - 0.5);
+    grid.temperature.iter_mut().for_each(|t| *t = lcg(&mut state));
+
+    let mut simulator = Simulator::new(grid.clone());
+    simulator.advect(dt);
+
+    // scalar semi-Lagrangian reference
+    let trace_back = |p: Vector2| p - dt * grid.vel(p);
+    for row in 0..cc {
+        for col in 0..=cc {
+            let vx = grid.vel(trace_back(vector2(col as f64, row as f64 + 0.5))).x;
+            let vy = grid.vel(trace_back(vector2(row as f64 + 0.5, col as f64))).y;
+            assert!((simulator.grid.vel_x_grid(col, row) - vx).abs() <= 1e-12);
+            assert!((simulator.grid.vel_y_grid(row, col) - vy).abs() <= 1e-12);
+        }
+    }
+
+    for y in 0..cc {
+        for x in 0..cc {
+            let temp = grid.temp(trace_back(vector2(x as f64 + 0.5, y as f64 + 0.5)));
+            assert!((simulator.grid.temp_grid(x, y) - temp).abs() <= 1e-12);
+        }
+    }
+}
+
+#[test]
+fn interpolate_2d_reproduces_linear() {
+    let f = |x: f64, y: f64| 2.0 * x - 3.0 * y + 1.0;
+    let sample = |i: i32, j: i32| f(i as f64, j as f64);
+
+    let mut state = 11;
+    for _ in 0..100 {
+        let (x, y) = (lcg(&mut state) * 10.0 - 5.0, lcg(&mut state) * 10.0 - 5.0);
+        for kind in Interpolation2DKind::ALL {
+            assert!((kind.interpolate_2d(&sample, x, y) - f(x, y)).abs() < 1e-10, "{}", kind.name());
+        }
+    }
+}
+
+#[test]
+fn interpolate_2d_batch_matches_scalar() {
+    let mut state = 5;
+    let values: Vec<f64> = (0..64).map(|_| lcg(&mut state)).collect();
+    let sample = |i: i32, j: i32| values[(i.clamp(0, 7) + 8 * j.clamp(0, 7)) as usize];
+
+    let xs: Vec<f64> = (0..37).map(|_| lcg(&mut state) * 7.0).collect();
+    let ys: Vec<f64> = (0..37).map(|_| lcg(&mut state) * 7.0).collect();
+
+    for kind in Interpolation2DKind::ALL {
+        let mut batched = vec![0.0; xs.len()];
+        kind.interpolate_2d_batch(&sample, &xs, &ys, &mut batched);
+
+        for i in 0..xs.len() {
+            assert!((kind.interpolate_2d(&sample, xs[i], ys[i]) - batched[i]).abs() <= 1e-12, "{}", kind.name());
+        }
+    }
+}
+
+#[test]
+fn grid_vel_samples_both_axes() {
+    let cc = 8;
+    let mut grid = StaggeredMACGrid::new(cc);
+
+    // x velocity varying only along y
+    for y in -1..=cc {
+        for x in -1..=cc + 1 {
+            *grid.vel_x_grid_mut(x, y) = y as f64;
+        }
+    }
+
+    for kind in Interpolation2DKind::ALL {
+        grid.vel_interpolation = kind;
+
+        // faces of row y sit at height y + 0.5
+        assert!((grid.vel(vector2(3.3, 2.5)).x - 2.0).abs() < 1e-10);
+        assert!((grid.vel(vector2(3.3, 3.0)).x - 2.5).abs() < 1e-10);
+        assert!((grid.vel(vector2(0.7, 4.25)).x - 3.75).abs() < 1e-10);
+    }
+}
+
+/// checks that `I` stays within the two enclosing samples and reproduces the samples themselves
+fn assert_no_new_extrema<I: Interpolation>(values: &[f64]) {
+    for c in 0..values.len() - 1 {
+        let (lo, hi) = (values[c].min(values[c + 1]), values[c].max(values[c + 1]));
+        assert!(I::interpolate(values, c as f64) == values[c]);
+
+        for k in 1..20 {
+            let v = I::interpolate(values, c as f64 + k as f64 / 20.0);
+            assert!(v >= lo && v <= hi, "{v} outside [{lo}, {hi}]");
+        }
+    }
+}
+
+#[test]
+fn monotone_cubic_interpolate() {
+    let mut state = 13;
+    let values: Vec<f64> = (0..50).map(|_| lcg(&mut state)).collect();
+    assert_no_new_extrema::<MonotoneCubicInterpolation>(&values);
+
+    // a step overshoots with the plain cubic kernel
+    let step = [0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+    assert!(CubicInterpolation::interpolate(&step, 1.5) < 0.0);
+    assert_no_new_extrema::<MonotoneCubicInterpolation>(&step);
+
+    // monotone data stays monotone
+    let increasing: Vec<f64> = (0..20).map(|x| (3.0 * (x as f64 - 10.0)).tanh() + 0.01 * x as f64).collect();
+    let mut last = f64::NEG_INFINITY;
+    for k in 0..=190 {
+        let v = MonotoneCubicInterpolation::interpolate(&increasing, k as f64 / 10.0);
+        assert!(v >= last);
+        last = v;
+    }
+}
+
+#[test]
+fn clamped_cubic_interpolate() {
+    let mut state = 17;
+    let values: Vec<f64> = (0..50).map(|_| lcg(&mut state) - 0.5).collect();
+    assert_no_new_extrema::<ClampedCubicInterpolation>(&values);
+    assert_no_new_extrema::<ClampedCubicInterpolation>(&[0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+
+    let indices: Vec<f64> = (0..99).map(|_| lcg(&mut state) * 49.0).collect();
+    let mut batched = vec![0.0; indices.len()];
+    ClampedCubicInterpolation::interpolate_batch(&values, &indices, &mut batched);
+    for (index, b) in indices.iter().zip(&batched) {
+        assert!((ClampedCubicInterpolation::interpolate(&values, *index) - b).abs() <= 1e-12);
+    }
+}
+
+#[test]
+fn limited_bicubic_stays_within_cell() {
+    let mut state = 19;
+    let values: Vec<f64> = (0..100).map(|_| lcg(&mut state)).collect();
+    let sample = |i: i32, j: i32| values[(i.clamp(0, 9) + 10 * j.clamp(0, 9)) as usize];
+
+    for kind in [Interpolation2DKind::MonotoneBicubic, Interpolation2DKind::ClampedBicubic] {
+        for _ in 0..1000 {
+            let (x, y) = (lcg(&mut state) * 9.0, lcg(&mut state) * 9.0);
+            let (i, j) = (x.floor() as i32, y.floor() as i32);
+            let corners = [sample(i, j), sample(i + 1, j), sample(i, j + 1), sample(i + 1, j + 1)];
+            let lo = corners.iter().cloned().fold(f64::INFINITY, f64::min);
+            let hi = corners.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+            let v = kind.interpolate_2d(&sample, x, y);
+            assert!(v >= lo - 1e-12 && v <= hi + 1e-12, "{}", kind.name());
+        }
+    }
+}
+
+#[test]
+fn advected_temperature_stays_positive() {
+    let cc = 16;
+    let mut grid = StaggeredMACGrid::new(cc);
+    grid.velocities_x.iter_mut().for_each(|v| *v = 0.7);
+    grid.velocities_y.iter_mut().for_each(|v| *v = 0.3);
+    *grid.temp_grid_mut(8, 8) = 10.0;
+
+    let mut simulator = Simulator::new(grid);
+    for _ in 0..10 {
+        simulator.advect(0.4);
+    }
+
+    assert!(simulator.grid.temperature.iter().all(|t| *t >= 0.0));
+}
+
+/// maximum error of `I` reconstructing `f` on [0, 1] from `n + 1` samples, measured between
+/// samples away from the ends so that the one-sided boundary stencils don't dominate
+fn interpolation_error<I: Interpolation>(f: fn(f64) -> f64, n: usize) -> f64 {
+    let h = 1.0 / n as f64;
+    let points: Vec<f64> = (0..=n).map(|k| f(k as f64 * h)).collect();
+
+    let indices: Vec<f64> = (n / 4..3 * n / 4)
+        .flat_map(|k| [0.25, 0.5, 0.75].map(|s| k as f64 + s))
+        .collect();
+    let mut values = vec![0.0; indices.len()];
+    I::interpolate_batch(&points, &indices, &mut values);
+
+    indices.iter().zip(values)
+        .map(|(index, v)| (v - f(index * h)).abs())
+        .fold(0.0, f64::max)
+}
+
+/// observed order of convergence of `I` under successive grid refinement
+fn convergence_order<I: Interpolation>(f: fn(f64) -> f64) -> f64 {
+    let coarse = interpolation_error::<I>(f, 128);
+    let fine = interpolation_error::<I>(f, 256);
+
+    (coarse / fine).log2()
+}
+
+#[test]
+fn interpolation_convergence_order() {
+    // with inflection points, where the WENO smoothness indicators can't tell the candidates apart
+    let smooth = |x: f64| (2.0 * std::f64::consts::PI * x).sin() + 0.5 * (5.0 * x).cos();
+
+    let orders = [
+        ("linear", convergence_order::<LinearInterpolation>(smooth), 2.0),
+        ("cubic", convergence_order::<CubicInterpolation>(smooth), 4.0),
+        ("Catmull-Rom", convergence_order::<CatmullRomInterpolation>(smooth), 3.0),
+        // the nonlinear weights guarantee one order above the candidate stencils in the maximum norm,
+        // the order of the full stencil is only reached away from inflection points
+        ("WENO4", convergence_order::<Weno4Interpolation>(smooth), 3.0),
+        ("WENO6", convergence_order::<Weno6Interpolation>(smooth), 4.0),
+        ("cubic B-spline", convergence_order::<CubicBSplineInterpolation>(smooth), 4.0)
+    ];
+
+    for (name, order, expected) in orders {
+        assert!((order - expected).abs() < 0.25, "{name}: observed order {order:.2}, expected {expected}");
+    }
+}
+
+#[test]
+fn weno_non_oscillatory() {
+    let step: Vec<f64> = (0..20).map(|x| if x < 10 { 0.0 } else { 1.0 }).collect();
+
+    // the WENO kernels overshoot far less than the linear kernel of the same width
+    let overshoot = |f: fn(&[f64], f64) -> f64| {
+        (0..190).map(|k| f(&step, k as f64 / 10.0)).map(|v| (-v).max(v - 1.0)).fold(0.0, f64::max)
+    };
+
+    assert!(overshoot(Weno4Interpolation::interpolate) < 0.1 * overshoot(CubicInterpolation::interpolate));
+    assert!(overshoot(Weno6Interpolation::interpolate) < 0.1 * overshoot(CubicInterpolation::interpolate));
+}
+
+#[test]
+fn bspline_interpolates_samples() {
+    let mut state = 23;
+    let values: Vec<f64> = (0..30).map(|_| lcg(&mut state)).collect();
+
+    for (k, v) in values.iter().enumerate() {
+        assert!((CubicBSplineInterpolation::interpolate(&values, k as f64) - v).abs() < 1e-12);
+    }
+
+    // prefiltered once, the spline matches the kernel between the samples too
+    let coefficients = CubicBSplineInterpolation::coefficients(&values);
+    for index in [0.5, 7.25, 28.9] {
+        assert!(CubicBSplineInterpolation::evaluate(&coefficients, index) == CubicBSplineInterpolation::interpolate(&values, index));
+    }
+}
+
+#[test]
+fn grid_sample_outside_domain() {
+    let cc = 10;
+    let mut grid = StaggeredMACGrid::new(cc);
+    grid.velocities_x.iter_mut().for_each(|v| *v = 1.0);
+    grid.velocities_y.iter_mut().for_each(|v| *v = -2.0);
+    grid.temperature.iter_mut().for_each(|t| *t = 3.0);
+
+    // negative and far-away positions are well defined
+    grid.boundary = BoundaryPolicy::Clamp;
+    for pos in [vector2(-0.3, -0.7), vector2(-50.0, 4.0), vector2(1e9, -1e9)] {
+        let v = grid.vel(pos);
+        assert!((v.x - 1.0).abs() < 1e-12 && (v.y + 2.0).abs() < 1e-12);
+        assert!((grid.temp(pos) - 3.0).abs() < 1e-12);
+    }
+
+    grid.boundary = BoundaryPolicy::Zero;
+    assert!(grid.vel(vector2(-20.0, 5.0)).len() == 0.0);
+    assert!(grid.temp(vector2(5.0, cc as f64 + 20.0)) == 0.0);
+
+    // periodic sampling repeats the interior
+    let mut state = 29;
+    grid.velocities_x.iter_mut().for_each(|v| *v = lcg(&mut state));
+    grid.velocities_y.iter_mut().for_each(|v| *v = lcg(&mut state));
+    grid.temperature.iter_mut().for_each(|t| *t = lcg(&mut state));
+    grid.boundary = BoundaryPolicy::Periodic;
+
+    for _ in 0..50 {
+        let pos = vector2(lcg(&mut state) * cc as f64, lcg(&mut state) * cc as f64);
+        let shifted = vector2(pos.x - 3.0 * cc as f64, pos.y + cc as f64);
+        assert!((grid.vel(pos) - grid.vel(shifted)).len() < 1e-9);
+        assert!((grid.temp(pos) - grid.temp(shifted)).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn grid_cell_centered_velocities() {
+    let cc = 6;
+    let mut grid = StaggeredMACGrid::new(cc);
+
+    // linear fields, so face averages are exact
+    for y in -1..=cc {
+        for x in -1..=cc + 1 {
+            *grid.vel_x_grid_mut(x, y) = x as f64;
+            *grid.vel_y_grid_mut(y, x) = 2.0 * x as f64;
+        }
+    }
+
+    let velocities = grid.cell_centered_velocities();
+    assert!(velocities.len() == (cc * cc) as usize);
+
+    for y in 0..cc {
+        for x in 0..cc {
+            let center = vector2(x as f64 + 0.5, y as f64 + 0.5);
+            let v = velocities[(x + y * cc) as usize];
+            assert!((v - vector2(center.x, 2.0 * center.y)).len() < 1e-12);
+            assert!((v - grid.vel(center)).len() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn rk4_solid_body_rotation() {
+    let cc = 20;
+    let c = cc as f64 / 2.0;
+    let mut grid = StaggeredMACGrid::new(cc);
+
+    // u = (-(y - c), x - c), linear so every kernel reproduces it exactly
+    for y in -1..=cc {
+        for x in -1..=cc + 1 {
+            *grid.vel_x_grid_mut(x, y) = -(y as f64 + 0.5 - c);
+            *grid.vel_y_grid_mut(y, x) = y as f64 + 0.5 - c;
+        }
+    }
+
+    // one full revolution returns to the start
+    let start = vector2(c + 4.0, c);
+    let steps = 200;
+    let h = 2.0 * std::f64::consts::PI / steps as f64;
+
+    let mut pos = start;
+    for _ in 0..steps {
+        pos = rk4(&grid, pos, h);
+        assert!(((pos - vector2(c, c)).len() - 4.0).abs() < 1e-6);
+    }
+
+    assert!((pos - start).len() < 1e-6);
+}
+
+/// grid of `cc` cells with the uniform velocity (1, 0)
+fn uniform_flow(cc: i32) -> StaggeredMACGrid {
+    let mut grid = StaggeredMACGrid::new(cc);
+    grid.velocities_x.iter_mut().for_each(|u| *u = 1.0);
+    grid
+}
+
+#[test]
+fn streamlines_follow_uniform_flow() {
+    let cc = 10;
+    let grid = uniform_flow(cc);
+    let flow_lines = FlowLines::new();
+
+    let line = flow_lines.streamline(&grid, vector2(5.0, 3.0));
+    assert!(line.iter().all(|p| (p.y - 3.0).abs() < 1e-12));
+    assert!(line.windows(2).all(|w| w[1].x > w[0].x));
+
+    // integrated backward and forward until the walls
+    let (first, last) = (line[0], line[line.len() - 1]);
+    assert!(first.x < flow_lines.streamline_step && last.x > cc as f64 - flow_lines.streamline_step);
+}
+
+#[test]
+fn pathlines_and_streaklines() {
+    let grid = uniform_flow(20);
+    let mut flow_lines = FlowLines::new();
+    flow_lines.history_length = 5;
+    let seed = vector2(2.0, 4.0);
+    flow_lines.add_seed(seed);
+
+    for _ in 0..8 {
+        flow_lines.step(&grid, 0.5);
+    }
+
+    // the pathline keeps the last positions of the particle released at the seed, oldest first
+    let pathline: Vec<Vector2> = flow_lines.pathlines()[0].iter().copied().collect();
+    assert!(pathline.len() == 5);
+    for (k, p) in pathline.iter().enumerate() {
+        assert!((*p - vector2(2.0 + 0.5 * (k + 4) as f64, 4.0)).len() < 1e-12);
+    }
+
+    // the streakline connects the particles released at the seed, newest first
+    let streakline: Vec<Vector2> = flow_lines.streaklines()[0].iter().copied().collect();
+    assert!(streakline.len() == 5);
+    for (k, p) in streakline.iter().enumerate() {
+        assert!((*p - vector2(2.0 + 0.5 * k as f64, 4.0)).len() < 1e-12);
+    }
+
+    flow_lines.reset_history();
+    assert!(flow_lines.pathlines()[0].len() == 1 && flow_lines.streaklines()[0][0] == seed);
+
+    flow_lines.add_rake(vector2(1.0, 1.0), vector2(1.0, 9.0), 5);
+    assert!(flow_lines.seeds().len() == 6 && flow_lines.seeds()[3] == vector2(1.0, 5.0));
+}
+
+#[test]
+fn lic_smears_noise_along_streamlines() {
+    let grid = uniform_flow(8);
+    let mut lic = Lic::new();
+    lic.resolution = 48;
+    lic.color_by_speed = false;
+
+    let image = lic.compute(&grid, &ColorScale::new(Colormap::Viridis, Normalization::Auto));
+    assert!(image.size == [48, 48] && image.pixels.len() == 48 * 48);
+
+    // neighbours along the horizontal streamlines average almost the same noise, neighbours across them don't
+    let value = |x: usize, y: usize| image.pixels[x + 48 * y].r() as f64;
+    let (mut along, mut across) = (0.0, 0.0);
+    for y in 0..47 {
+        for x in 16..32 {
+            along += (value(x + 1, y) - value(x, y)).abs();
+            across += (value(x, y + 1) - value(x, y)).abs();
+        }
+    }
+    assert!(along < 0.25 * across, "along {along}, across {across}");
+}
+
+#[test]
+fn particles_advect_age_and_emit() {
+    let cc = 10;
+    let mut grid = StaggeredMACGrid::new(cc);
+    grid.velocities_x.iter_mut().for_each(|v| *v = 0.5);
+
+    let mut particles = Particles::new();
+    particles.lifetime = 1.0;
+    particles.trail_length = 3;
+    particles.add(vector2(2.0, 5.0));
+    particles.emitters.push(Emitter::new(vector2(1.0, 1.0), 10.0, 0.5));
+
+    for _ in 0..5 {
+        particles.step(&grid, 0.1);
+    }
+
+    // the first particle moved with the flow and keeps only the most recent trail
+    let first = &particles.particles[0];
+    assert!((first.pos - vector2(2.25, 5.0)).len() < 1e-9);
+    assert!(first.trail.len() == 3);
+    assert!((*first.trail.back().unwrap() - vector2(2.2, 5.0)).len() < 1e-9);
+
+    // one emitted particle per step, all within the emitter radius
+    assert!(particles.particles.len() == 6);
+    for p in &particles.particles[1..] {
+        assert!((p.pos - vector2(1.0, 1.0)).len() <= 0.5 + 0.5);
+    }
+
+    // expired particles are removed, emission is capped
+    particles.max_count = 8;
+    for _ in 0..10 {
+        particles.step(&grid, 0.1);
+    }
+    assert!(particles.particles.iter().all(|p| p.age <= 1.0));
+    assert!(particles.particles.len() <= 8);
+}
+
+#[test]
+fn particles_leave_or_wrap_around_the_domain() {
+    let mut grid = uniform_flow(10);
+    let mut particles = Particles::new();
+    particles.add(vector2(9.5, 5.0));
+    particles.add(vector2(5.0, 5.0));
+
+    particles.step(&grid, 1.0);
+    assert!(particles.particles.len() == 1 && (particles.particles[0].pos - vector2(6.0, 5.0)).len() < 1e-9);
+
+    grid.boundary = BoundaryPolicy::Periodic;
+    particles.add(vector2(9.5, 2.0));
+    particles.step(&grid, 1.0);
+    assert!(particles.particles.len() == 2);
+    assert!((particles.particles[1].pos - vector2(0.5, 2.0)).len() < 1e-9 && particles.particles[1].trail.is_empty());
+}
+
+#[test]
+fn simulator_steps_particles() {
+    let mut grid = StaggeredMACGrid::new(8);
+    grid.velocities_y.iter_mut().for_each(|v| *v = 1.0);
+
+    let mut simulator = Simulator::new(grid);
+    simulator.particles.add(vector2(4.0, 2.0));
+    simulator.advect(0.5);
+
+    assert!((simulator.particles.particles[0].pos - vector2(4.0, 2.5)).len() < 1e-9);
+}
+
+#[test]
+fn color_scale_normalization() {
+    let values = [-1.0, 0.5, 3.0, f64::NAN];
+
+    let mut scale = ColorScale::new(Colormap::Viridis, Normalization::Auto);
+    scale.update_range(values);
+    assert!(scale.range() == (-1.0, 3.0));
+    assert!(scale.normalize(1.0) == 0.5);
+    assert!(scale.normalize(10.0) == 1.0);
+
+    scale.normalization = Normalization::AutoSymmetric;
+    scale.update_range(values);
+    assert!(scale.range() == (-3.0, 3.0));
+    assert!(scale.normalize(0.0) == 0.5);
+
+    scale.normalization = Normalization::Fixed;
+    scale.fixed_min = 0.0;
+    scale.fixed_max = 2.0;
+    scale.update_range(values);
+    assert!(scale.normalize(0.5) == 0.25);
+
+    // empty or constant data maps to the middle of the color map
+    scale.normalization = Normalization::Auto;
+    scale.update_range([]);
+    assert!(scale.normalize(0.0) == 0.5);
+}
+
+#[test]
+fn colormap_endpoints() {
+    let mut scale = ColorScale::new(Colormap::Viridis, Normalization::Auto);
+    assert!(scale.sample(0.0) == Color32::from_rgb(68, 1, 84));
+    assert!(scale.sample(1.0) == Color32::from_rgb(253, 231, 37));
+
+    scale.colormap = Colormap::Coolwarm;
+    assert!(scale.sample(0.5) == Color32::from_gray(221));
+
+    scale.colormap = Colormap::Custom;
+    scale.custom = Gradient { stops: vec![(0.0, Color32::BLACK), (1.0, Color32::WHITE)] };
+    assert!(scale.sample(0.5) == Color32::from_gray(128));
+    assert!(scale.sample(-1.0) == Color32::BLACK);
+
+    // every map is defined on the whole unit interval
+    for colormap in Colormap::ALL {
+        scale.colormap = colormap;
+        for i in 0..=100 {
+            scale.sample(i as f32 / 100.0);
+        }
+    }
+}
+
+#[test]
+fn grid_raster_and_cell_values() {
+    let cc = 4;
+    let mut grid = StaggeredMACGrid::new(cc);
+    for y in 0..cc {
+        for x in 0..cc {
+            *grid.temp_grid_mut(x, y) = (x + 10 * y) as f64;
+        }
+    }
+
+    let temperatures = grid.cell_temperatures();
+    assert!(temperatures.len() == 16);
+    assert!(temperatures[1 + 2 * 4] == 21.0);
+
+    // one texel per cell samples exactly the cell centers
+    let positions = grid.raster_positions(4);
+    assert!(positions[1 + 2 * 4] == vector2(1.5, 2.5));
+    let mut sampled = vec![0.0; positions.len()];
+    grid.temp_batch(&positions, &mut sampled);
+    assert!(sampled.iter().zip(&temperatures).all(|(a, b)| (a - b).abs() < 1e-12));
+
+    // upsampled rasters stay within the domain
+    let positions = grid.raster_positions(16);
+    assert!(positions[0] == vector2(0.125, 0.125));
+    assert!(positions[255] == vector2(3.875, 3.875));
+}
+
+/// random interior velocities with walls (zero normal velocity) on the domain boundary
+fn random_walled_grid(cc: i32, seed: u64) -> StaggeredMACGrid {
+    let mut state = seed;
+    let mut grid = StaggeredMACGrid::new(cc);
+    for y in 0..cc {
+        for x in 1..cc {
+            *grid.vel_x_grid_mut(x, y) = lcg(&mut state) - 0.5;
+            *grid.vel_y_grid_mut(y, x) = lcg(&mut state) - 0.5;
+        }
+    }
+    grid
+}
+
+#[test]
+fn projection_removes_divergence() {
+    let solver = PressureSolver { max_iterations: 5000, tolerance: 1e-10 };
+
+    for boundary in [BoundaryPolicy::Clamp, BoundaryPolicy::Periodic] {
+        let mut grid = random_walled_grid(16, 7);
+        grid.boundary = boundary;
+        let walls = (grid.vel_x_grid(0, 3), grid.vel_y_grid(5, 16));
+
+        let mut pressure = Vec::new();
+        let stats = solver.project(&mut grid, 0.5, &mut pressure);
+
+        assert!(stats.iterations < solver.max_iterations);
+        let max_divergence = PressureSolver::divergence(&grid).iter().fold(0.0, |m: f64, d| m.max(d.abs()));
+        assert!(max_divergence < 1e-8, "{:?}: {max_divergence}", boundary);
+
+        if boundary == BoundaryPolicy::Clamp {
+            assert!((grid.vel_x_grid(0, 3), grid.vel_y_grid(5, 16)) == walls);
+        }
+
+        // a divergence-free field needs no further correction
+        let mut correction = Vec::new();
+        solver.solve(&grid, 0.5, &mut correction);
+        assert!(correction.iter().all(|p| p.abs() < 1e-6));
+    }
+}
+
+#[test]
+fn simulator_step_projects() {
+    let mut simulator = Simulator::new(random_walled_grid(12, 3));
+    simulator.projection = true;
+    simulator.pressure_solver.tolerance = 1e-9;
+    simulator.step(0.1);
+
+    assert!(simulator.pressure.len() == 144);
+    assert!(simulator.projection_stats.residual < 1e-9);
+    let divergence = DerivedField::Divergence.compute(&simulator.grid, &simulator.pressure);
+    assert!(divergence.iter().all(|d| d.abs() < 1e-8));
+}
+
+#[test]
+fn derived_fields_of_linear_flows() {
+    let cc = 8;
+    // u = a x + b y, v = c x + d y sampled at the faces (including ghosts)
+    let (a, b, c, d) = (0.3, -0.7, 0.5, 0.2);
+    let mut grid = StaggeredMACGrid::new(cc);
+    for y in -1..=cc {
+        for x in -1..=cc + 1 {
+            *grid.vel_x_grid_mut(x, y) = a * x as f64 + b * (y as f64 + 0.5);
+            *grid.vel_y_grid_mut(y, x) = c * (y as f64 + 0.5) + d * x as f64;
+        }
+    }
+
+    let check = |field: DerivedField, expected: f64| {
+        let values = field.compute(&grid, &[]);
+        assert!(values.len() == 64);
+        assert!(values.iter().all(|v| (v - expected).abs() < 1e-12), "{}: {} != {expected}", field.name(), values[0]);
+    };
+
+    check(DerivedField::Divergence, a + d);
+    check(DerivedField::Vorticity, c - b);
+    check(DerivedField::QCriterion, -0.5 * (a * a + d * d) - b * c);
+    check(DerivedField::Pressure, 0.0);
+
+    let speed = DerivedField::Speed.compute(&grid, &[]);
+    assert!((speed[0] - grid.vel_center(0, 0).len()).abs() < 1e-12);
+}
+
+#[test]
+fn brush_paints_and_erases() {
+    let cc = 10;
+    let mut grid = StaggeredMACGrid::new(cc);
+    let brush = Brush::new(2.0, 0.5);
+    let center = vector2(5.0, 5.0);
+
+    brush.paint_dye(&mut grid, center, 1.0);
+    brush.paint_temperature(&mut grid, center, -2.0, 1.0);
+    // strongest at the center, nothing outside the radius
+    assert!(grid.dye_grid(4, 4) > grid.dye_grid(3, 4) && grid.dye_grid(3, 4) > 0.0);
+    assert!(grid.dye_grid(2, 4) == 0.0 && grid.dye_grid(7, 4) == 0.0);
+    assert!((grid.temp_grid(4, 4) + 2.0 * grid.dye_grid(4, 4)).abs() < 1e-12);
+
+    brush.paint_velocity(&mut grid, center, vector2(1.0, -1.0), 1.0);
+    assert!(grid.vel_x_grid(5, 4) > 0.0 && grid.vel_y_grid(4, 5) < 0.0);
+    assert!(grid.vel_x_grid(5, 4) == -grid.vel_y_grid(4, 5));
+
+    // walls stay closed
+    brush.paint_velocity(&mut grid, vector2(0.5, 0.5), vector2(1.0, 1.0), 1.0);
+    assert!(grid.vel_x_grid(0, 0) == 0.0 && grid.vel_y_grid(0, 0) == 0.0);
+    assert!(grid.vel_x_grid(1, 0) > 0.0);
+
+    // painting for a second in many short frames or in a single one gives the same result
+    let mut frames = grid.clone();
+    for _ in 0..50 {
+        brush.paint_dye(&mut frames, center, 0.02);
+    }
+    brush.paint_dye(&mut grid, center, 1.0);
+    assert!(frames.dye.iter().zip(&grid.dye).all(|(a, b)| (a - b).abs() < 1e-12));
+
+    let eraser = Brush::new(3.0, 50.0);
+    let (dye_before, vel_before) = (grid.dye_grid(3, 4), grid.vel_x_grid(5, 4));
+    eraser.erase(&mut grid, vector2(4.5, 4.5), 1.0);
+    assert!(grid.dye_grid(4, 4).abs() < 1e-12 && grid.temp_grid(4, 4).abs() < 1e-12);
+    assert!(grid.dye_grid(3, 4) < dye_before && grid.vel_x_grid(5, 4) < vel_before);
+
+    for _ in 0..50 {
+        eraser.erase(&mut frames, vector2(4.5, 4.5), 0.02);
+    }
+    assert!(frames.dye.iter().zip(&grid.dye).all(|(a, b)| (a - b).abs() < 1e-9));
+}
+
+#[test]
+fn dye_is_advected() {
+    let mut grid = StaggeredMACGrid::new(8);
+    grid.velocities_x.iter_mut().for_each(|v| *v = 1.0);
+    *grid.dye_grid_mut(3, 4) = 1.0;
+
+    let mut simulator = Simulator::new(grid);
+    simulator.advect(1.0);
+
+    // moved one cell downstream
+    assert!((simulator.grid.dye_grid(4, 4) - 1.0).abs() < 1e-12);
+    assert!(simulator.grid.dye_grid(3, 4).abs() < 1e-12);
+}
+
+#[test]
+fn obstacle_shapes() {
+    let mut rect = Obstacle::rectangle(vector2(3.0, 4.0), vector2(1.0, 2.0));
+    assert!(rect.bounds() == (vector2(1.0, 2.0), vector2(3.0, 4.0)));
+    assert!(rect.contains(vector2(2.0, 3.0)) && !rect.contains(vector2(3.5, 3.0)));
+
+    rect.translate(vector2(1.0, 0.0));
+    assert!(rect.contains(vector2(3.5, 3.0)));
+    rect.resize(vector2(0.0, 0.0), vector2(1.0, 1.0));
+    assert!(rect.bounds() == (vector2(0.0, 0.0), vector2(1.0, 1.0)));
+
+    let mut circle = Obstacle::Circle { center: vector2(5.0, 5.0), radius: 2.0 };
+    assert!(circle.contains(vector2(6.9, 5.0)) && !circle.contains(vector2(6.5, 6.5)));
+    circle.resize(vector2(0.0, 0.0), vector2(4.0, 2.0));
+    assert!(circle == Obstacle::Circle { center: vector2(2.0, 1.0), radius: 1.0 });
+
+    // concave L shape
+    let mut polygon = Obstacle::Polygon { points: vec![
+        vector2(0.0, 0.0), vector2(4.0, 0.0), vector2(4.0, 1.0), vector2(1.0, 1.0), vector2(1.0, 4.0), vector2(0.0, 4.0)
+    ] };
+    assert!(polygon.contains(vector2(3.0, 0.5)) && polygon.contains(vector2(0.5, 3.0)));
+    assert!(!polygon.contains(vector2(2.0, 2.0)));
+    polygon.resize(vector2(0.0, 0.0), vector2(8.0, 8.0));
+    assert!(polygon.contains(vector2(6.0, 1.0)) && !polygon.contains(vector2(4.0, 4.0)));
+}
+
+#[test]
+fn obstacles_block_flow() {
+    let mut grid = random_walled_grid(16, 11);
+    *grid.dye_grid_mut(8, 8) = 1.0;
+    grid.obstacles.push(Obstacle::Circle { center: vector2(8.0, 8.0), radius: 3.0 });
+    grid.update_obstacles();
+
+    assert!(grid.is_solid(8, 8) && grid.is_solid(7, 7) && !grid.is_solid(2, 2) && !grid.is_solid(-1, 0));
+    assert!(grid.dye_grid(8, 8) == 0.0 && grid.vel_x_grid(8, 8) == 0.0);
+
+    let mut simulator = Simulator::new(grid);
+    simulator.projection = true;
+    simulator.pressure_solver.tolerance = 1e-10;
+    simulator.pressure_solver.max_iterations = 5000;
+    simulator.step(0.2);
+    let grid = &simulator.grid;
+
+    // nothing flows into the obstacle and the fluid is divergence free around it
+    let divergence = PressureSolver::divergence(grid);
+    for y in 0..16 {
+        for x in 0..16 {
+            if grid.is_solid(x, y) {
+                assert!(grid.vel_x_grid(x, y) == 0.0 && grid.vel_x_grid(x + 1, y) == 0.0);
+                assert!(grid.vel_y_grid(x, y) == 0.0 && grid.vel_y_grid(x, y + 1) == 0.0);
+                assert!(simulator.pressure[(x + y * 16) as usize] == 0.0);
+            } else {
+                assert!(divergence[(x + y * 16) as usize].abs() < 1e-8);
+            }
+        }
+    }
+}
+
+#[test]
+fn diagnostics_are_recorded() {
+    let cc = 8;
+    let mut grid = StaggeredMACGrid::new(cc);
+    // uniform flow in a periodic box stays uniform
+    grid.boundary = BoundaryPolicy::Periodic;
+    grid.velocities_x.iter_mut().for_each(|v| *v = 0.5);
+    grid.temperature.iter_mut().for_each(|t| *t = 2.0);
+
+    let mut simulator = Simulator::new(grid);
+    simulator.diagnostics.capacity = 3;
+    for _ in 0..5 {
+        simulator.step(0.4);
+    }
+
+    let samples = simulator.diagnostics.samples();
+    assert!(samples.len() == 3);
+    let last = samples.back().unwrap();
+    assert!((last.time - 2.0).abs() < 1e-12);
+    assert!((last.kinetic_energy - 0.5 * 0.25 * 64.0).abs() < 1e-9);
+    assert!(last.enstrophy.abs() < 1e-12 && last.max_divergence < 1e-12);
+    assert!((last.temperature_mass - 128.0).abs() < 1e-9);
+    assert!((last.cfl - 0.2).abs() < 1e-12);
+
+    let series = simulator.diagnostics.series(DiagnosticSeries::Cfl);
+    assert!(series.len() == 3 && (series[0].0 - 1.2).abs() < 1e-12);
+
+    let csv = simulator.diagnostics.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert!(lines.len() == 4);
+    assert!(lines[0].starts_with("time,kinetic_energy,"));
+    assert!(lines[1].split(',').count() == DiagnosticSeries::ALL.len() + 1);
+}
+
+#[test]
+fn probes_record_interpolated_values() {
+    let cc = 8;
+    let mut grid = StaggeredMACGrid::new(cc);
+    grid.boundary = BoundaryPolicy::Periodic;
+    grid.velocities_y.iter_mut().for_each(|v| *v = 0.25);
+    for y in 0..cc {
+        for x in 0..cc {
+            *grid.temp_grid_mut(x, y) = x as f64;
+        }
+    }
+
+    let mut simulator = Simulator::new(grid);
+    simulator.probes.add(vector2(3.5, 2.0));
+    simulator.probes.add(vector2(4.0, 6.5));
+    simulator.probes.probes[1].name = String::from("wake, \"far\"");
+    simulator.probes.capacity = 2;
+    for _ in 0..3 {
+        simulator.step(0.5);
+    }
+
+    let probe = &simulator.probes.probes[0];
+    assert!(probe.name == "probe 1");
+    assert!(probe.history().len() == 2);
+    let last = probe.history().back().unwrap();
+    assert!((last.time - 1.5).abs() < 1e-12);
+    assert!((last.velocity - vector2(0.0, 0.25)).len() < 1e-12);
+    // vertical flow leaves the x-dependent temperature unchanged
+    assert!((last.temperature - 3.0).abs() < 1e-9);
+    assert!(probe.series(ProbeQuantity::Speed).iter().all(|(_, s)| (s - 0.25).abs() < 1e-12));
+
+    // halfway between two cells the pressure is interpolated
+    let pressure: Vec<f64> = (0..64).map(|i| (i % 8) as f64).collect();
+    assert!((simulator.grid.cell_field(&pressure, vector2(4.0, 2.5)) - 3.5).abs() < 1e-12);
+
+    let csv = simulator.probes.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert!(lines.len() == 5);
+    assert!(lines[0] == "probe,x,y,time,u,v,pressure,temperature,dye");
+    assert!(lines[3].starts_with("\"wake, \"\"far\"\"\",4,6.5,"));
+}
+
+#[test]
+fn snapshots_round_trip() {
+    let mut grid = random_walled_grid(12, 17);
+    grid.boundary = BoundaryPolicy::Zero;
+    grid.temp_interpolation = Interpolation2DKind::ALL[2];
+    let mut state = 5;
+    grid.dye.iter_mut().for_each(|d| *d = lcg(&mut state));
+    grid.temperature.iter_mut().for_each(|t| *t = lcg(&mut state) - 0.5);
+    grid.obstacles.push(Obstacle::Circle { center: vector2(4.0, 4.0), radius: 1.5 });
+    grid.obstacles.push(Obstacle::Polygon { points: vec![vector2(7.0, 7.0), vector2(10.0, 7.5), vector2(8.0, 10.0)] });
+    grid.update_obstacles();
+
+    let mut simulator = Simulator::new(grid);
+    simulator.projection = true;
+    simulator.pressure_solver.max_iterations = 123;
+    simulator.forces.buoyancy = 0.3;
+    simulator.forces.sources.push(Patch { field: GridField::Dye, value: 1.0, region: Obstacle::Circle { center: vector2(2.0, 9.0), radius: 1.0 } });
+    simulator.step(0.1);
+    let first = Snapshot::new(&simulator, 0.1);
+    simulator.projection = false;
+    simulator.forces = Forces::new();
+    simulator.step(0.1);
+    let second = Snapshot::new(&simulator, 0.05);
+
+    let dir = std::env::temp_dir().join(format!("flowy-snapshot-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let single = dir.join("single.flowy");
+    let collection = dir.join("collection.flowy");
+
+    second.save(&single).unwrap();
+    let loaded = Snapshot::load(&single).unwrap();
+    assert!(loaded == second);
+    assert!((0..12).all(|y| (0..12).all(|x| loaded.grid.is_solid(x, y) == second.grid.is_solid(x, y))));
+
+    snapshot::save_collection(&[first.clone(), second.clone()], &collection).unwrap();
+    let loaded = snapshot::load_collection(&collection).unwrap();
+    assert!(loaded.len() == 2 && loaded[0] == first && loaded[1] == second);
+
+    // restoring puts the state and settings back
+    let mut restored = Simulator::new(StaggeredMACGrid::new(4));
+    loaded[0].apply(&mut restored);
+    assert!(restored.grid == first.grid && restored.current_time_step == 1 && restored.projection);
+    assert!(restored.pressure_solver.max_iterations == 123 && restored.forces == first.forces);
+
+    // corrupt files are rejected instead of producing broken grids
+    let bytes = std::fs::read(&single).unwrap();
+    assert!(matches!(snapshot::collection_from_bytes(&bytes), Err(FormatError::WrongKind { .. })));
+    assert!(matches!(Snapshot::from_bytes(&bytes[..bytes.len() - 3]), Err(FormatError::Truncated)));
+    let mut newer = bytes.clone();
+    newer[8] = 3;
+    assert!(matches!(Snapshot::from_bytes(&newer), Err(FormatError::UnsupportedVersion(3))));
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(matches!(Snapshot::from_bytes(&trailing), Err(FormatError::Invalid(_))));
+
+    // settings nothing could be stepped with: the time step after the header, step count and time
+    for dt in [0.0, -0.1, f64::NAN, f64::INFINITY] {
+        let mut bad = bytes.clone();
+        bad[24..32].copy_from_slice(&dt.to_le_bytes());
+        assert!(matches!(Snapshot::from_bytes(&bad), Err(FormatError::Invalid(_))));
+    }
+    let mut bad = bytes.clone();
+    bad[33..41].copy_from_slice(&0u64.to_le_bytes());
+    assert!(matches!(Snapshot::from_bytes(&bad), Err(FormatError::Invalid(_))));
+    // a corrupt cell count is caught by the lengths of the stored fields, before allocating the grid
+    let mut bad = bytes.clone();
+    bad[49..53].copy_from_slice(&(1u32 << 14).to_le_bytes());
+    assert!(matches!(Snapshot::from_bytes(&bad), Err(FormatError::Invalid(e)) if e.contains("16384 cells")));
+
+    // version 1 ended after the grid and restores without forces
+    let without_forces = Snapshot { forces: Forces::new(), ..first.clone() };
+    without_forces.save(&single).unwrap();
+    let mut old = std::fs::read(&single).unwrap();
+    // gravity, buoyancy, ambient temperature and no sources
+    old.truncate(old.len() - 40);
+    old[8] = 1;
+    assert!(Snapshot::from_bytes(&old).unwrap() == without_forces);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn checkpoint_restart_matches_uninterrupted_run() {
+    let setup = || {
+        let mut grid = random_walled_grid(16, 23);
+        grid.obstacles.push(Obstacle::rectangle(vector2(6.0, 6.0), vector2(9.0, 8.0)));
+        grid.update_obstacles();
+        let mut simulator = Simulator::new(grid);
+        simulator.particles.emitters.push(Emitter::new(vector2(3.0, 12.0), 7.3, 1.5));
+        simulator.particles.add_cluster(vector2(10.0, 4.0), 2.0, 25);
+        simulator.probes.add(vector2(12.5, 11.0));
+        simulator.pressure_solver.tolerance = 1e-9;
+        simulator.forces.buoyancy = 0.2;
+        simulator.forces.sources.push(Patch { field: GridField::Dye, value: 1.0, region: Obstacle::Circle { center: vector2(4.0, 4.0), radius: 2.0 } });
+        simulator
+    };
+
+    let dir = std::env::temp_dir().join(format!("flowy-checkpoint-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("run.flowy");
+
+    let mut uninterrupted = setup();
+    for _ in 0..20 {
+        uninterrupted.step(0.3);
+    }
+
+    // periodic checkpoints, the run is "killed" after step 13 so the last checkpoint is from step 12
+    let mut killed = setup();
+    killed.checkpoints.interval = 4;
+    killed.checkpoints.path = path.clone();
+    for _ in 0..13 {
+        killed.step(0.3);
+    }
+    assert!(killed.checkpoints.last == Some(Ok(12)));
+
+    // resumed like `flowy-cli --resume`, which has to bring back the time step as well
+    let options = RunOptions { resume: Some(path.clone()), ..RunOptions::new() };
+    let (mut restarted, dt) = options.initial_state().unwrap();
+    assert!(restarted.current_time_step == 12 && dt == 0.3);
+    for _ in 12..20 {
+        restarted.step(dt);
+    }
+
+    assert!(restarted.grid == uninterrupted.grid);
+    assert!(restarted.pressure == uninterrupted.pressure);
+    assert!(restarted.particles == uninterrupted.particles);
+    assert!(restarted.probes == uninterrupted.probes);
+    assert!(restarted.forces == uninterrupted.forces);
+    assert!(restarted.time == uninterrupted.time && restarted.current_time_step == 20);
+    // everything but the wall-clock step times
+    let measured = |s: &Simulator| s.diagnostics.samples().iter().map(|d| (d.time, d.kinetic_energy, d.max_divergence, d.solver_iterations)).collect::<Vec<_>>();
+    assert!(measured(&restarted) == measured(&uninterrupted));
+
+    // solver settings nothing could be stepped with: no or endless iterations after the header, step count, time, dt and projection
+    let bytes = std::fs::read(&path).unwrap();
+    for iterations in [0, u64::MAX] {
+        let mut bad = bytes.clone();
+        bad[33..41].copy_from_slice(&iterations.to_le_bytes());
+        std::fs::write(&path, &bad).unwrap();
+        assert!(matches!(restarted.restore_checkpoint(&path), Err(FormatError::Invalid(_))));
+    }
+
+    // a failed restore leaves the simulator untouched
+    std::fs::write(&path, b"FLOWYCKP").unwrap();
+    assert!(matches!(restarted.restore_checkpoint(&path), Err(FormatError::Truncated)));
+    assert!(restarted.grid == uninterrupted.grid);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn vtk_export_layout() {
+    let cc = 6;
+    let mut grid = random_walled_grid(cc, 31);
+    for y in 0..cc {
+        for x in 0..cc {
+            *grid.temp_grid_mut(x, y) = (x + 10 * y) as f64;
+        }
+    }
+    let pressure = vec![0.5; (cc * cc) as usize];
+
+    // the values following a header line, up to the next header
+    let block = |text: &str, header: &str| -> Vec<f64> {
+        let lines: Vec<&str> = text.lines().collect();
+        let start = lines.iter().position(|l| l.trim() == header).unwrap() + 1;
+        lines[start..].iter()
+            .take_while(|l| l.trim_start().starts_with(|c: char| c.is_ascii_digit() || c == '-'))
+            .flat_map(|l| l.split_whitespace().map(|v| v.parse::<f64>().unwrap()))
+            .collect()
+    };
+
+    let legacy = vtk::legacy(&grid, &pressure, 2.5);
+    assert!(legacy.starts_with("# vtk DataFile Version 3.0\n"));
+    assert!(legacy.contains("DIMENSIONS 7 7 1\n") && legacy.contains("CELL_DATA 36\n") && legacy.contains("POINT_DATA 49\n"));
+    let temperatures = block(&legacy, "LOOKUP_TABLE default");
+    assert!(temperatures == grid.cell_temperatures());
+    let velocities = block(&legacy, "VECTORS velocity double");
+    assert!(velocities.len() == 3 * 36);
+    assert!(velocities[3 * 7] == grid.vel_center(1, 1).x && velocities[3 * 7 + 1] == grid.vel_center(1, 1).y);
+    for name in ["dye", "solid", "vorticity", "divergence", "pressure", "q_criterion"] {
+        assert!(legacy.contains(&format!("SCALARS {name} double 1")), "{name}");
+    }
+
+    let xml = vtk::image_data(&grid, &pressure, 2.5);
+    assert!(xml.contains("WholeExtent=\"0 6 0 6 0 0\""));
+    assert!(block(&xml, "<DataArray type=\"Float64\" Name=\"pressure\" format=\"ascii\">").len() == 49);
+    assert!(xml.matches("<DataArray").count() == xml.matches("</DataArray>").count());
+
+    // tiny values stay short and non-finite ones are written as numbers ParaView accepts
+    *grid.temp_grid_mut(0, 0) = 5e-324;
+    *grid.temp_grid_mut(1, 0) = 1e-30;
+    *grid.temp_grid_mut(2, 0) = f64::NAN;
+    *grid.temp_grid_mut(3, 0) = f64::NEG_INFINITY;
+    let legacy = vtk::legacy(&grid, &pressure, 2.5);
+    assert!(legacy.split_whitespace().all(|token| token.len() < 30));
+    assert!(block(&legacy, "LOOKUP_TABLE default")[..4] == [5e-324, 1e-30, 0.0, f64::MIN]);
+    assert!(!legacy.to_lowercase().contains("nan") && !legacy.contains("inf"));
+
+    let dir = std::env::temp_dir().join(format!("flowy-vtk-test-{}", std::process::id()));
+    let mut series = VtkSeries::new(&dir, "run");
+    series.write_step(&grid, &pressure, 0.0, 0).unwrap();
+    series.write_step(&grid, &pressure, 0.25, 1).unwrap();
+    assert!(dir.join("run_000001.vti").exists());
+    let pvd = std::fs::read_to_string(series.collection_path()).unwrap();
+    assert!(pvd.contains("<DataSet timestep=\"0.25\" group=\"\" part=\"0\" file=\"run_000001.vti\"/>"));
+    assert!(pvd.matches("<DataSet").count() == 2);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn field_arrays_round_trip() {
+    let cc = 5;
+    let mut grid = random_walled_grid(cc, 41);
+    let mut state = 9;
+    grid.temperature.iter_mut().for_each(|t| *t = lcg(&mut state) * 1e-7);
+    grid.dye.iter_mut().for_each(|d| *d = lcg(&mut state));
+
+    let dir = std::env::temp_dir().join(format!("flowy-arrays-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for field in GridField::ALL {
+        assert!(field.values(&grid).len() == field.shape(cc).0 * field.shape(cc).1);
+        for extension in ["csv", "npy"] {
+            let path = dir.join(format!("{}.{extension}", field.key()));
+            field.export(&grid, &path).unwrap();
+            let mut imported = StaggeredMACGrid::new(cc);
+            field.import(&mut imported, &path).unwrap();
+            assert!(field.values(&imported) == field.values(&grid), "{} {extension}", field.name());
+        }
+    }
+
+    // [y][x] layout: the second row of u holds the faces of row y = 1
+    let (shape, values) = arrays::from_csv(&arrays::to_csv(GridField::VelocityX.shape(cc), &GridField::VelocityX.values(&grid))).unwrap();
+    assert!(shape == (5, 6) && values[6 + 2] == grid.vel_x_grid(2, 1));
+
+    let npz = dir.join("fields.npz");
+    arrays::export_npz(&grid, &npz).unwrap();
+    let mut imported = StaggeredMACGrid::new(cc);
+    assert!(arrays::import_npz(&mut imported, &npz).unwrap() == GridField::ALL.to_vec());
+    assert!(GridField::ALL.iter().all(|f| f.values(&imported) == f.values(&grid)));
+
+    // fields of a different grid size are rejected and leave the grid untouched
+    let mut small = StaggeredMACGrid::new(4);
+    assert!(matches!(arrays::import_npz(&mut small, &npz), Err(FormatError::Shape { .. })));
+    assert!(small == StaggeredMACGrid::new(4));
+    assert!(matches!(GridField::Dye.import(&mut small, &dir.join("dye.csv")), Err(FormatError::Shape { expected, found }) if expected == vec![4, 4] && found == vec![5, 5]));
+
+    std::fs::write(dir.join("ragged.csv"), "1,2,3\n4,5\n").unwrap();
+    assert!(matches!(GridField::Dye.import(&mut small, &dir.join("ragged.csv")), Err(FormatError::Invalid(_))));
+
+    // an entry whose zip64 size would overflow the end of its contents
+    let mut hostile = 0x04034b50u32.to_le_bytes().to_vec();
+    hostile.resize(30, 0);
+    let mut entry = 0x02014b50u32.to_le_bytes().to_vec();
+    entry.resize(46, 0);
+    entry[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+    entry[30..32].copy_from_slice(&12u16.to_le_bytes());
+    entry.extend_from_slice(&[1, 0, 8, 0]);
+    entry.extend_from_slice(&u64::MAX.to_le_bytes());
+    hostile.extend_from_slice(&entry);
+    hostile.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    hostile.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+    hostile.extend_from_slice(&(entry.len() as u32).to_le_bytes());
+    hostile.extend_from_slice(&30u32.to_le_bytes());
+    hostile.extend_from_slice(&[0, 0]);
+    std::fs::write(dir.join("hostile.npz"), &hostile).unwrap();
+    assert!(matches!(arrays::import_npz(&mut small, &dir.join("hostile.npz")), Err(FormatError::Truncated)));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn npy_dtypes_and_order() {
+    let npy = |descr: &str, shape: &str, body: &[u8]| {
+        let header = format!("{{'descr': '{descr}', 'fortran_order': {}, 'shape': {shape}, }}\n", if descr.starts_with('>') { "True" } else { "False" });
+        let mut data = b"\x93NUMPY\x01\x00".to_vec();
+        data.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        data.extend_from_slice(header.as_bytes());
+        data.extend_from_slice(body);
+        data
+    };
+
+    let ints: Vec<u8> = [1i32, 2, 3, 4, 5, 6].iter().flat_map(|v| v.to_le_bytes()).collect();
+    let (shape, values) = arrays::from_npy(&npy("<i4", "(2, 3)", &ints)).unwrap();
+    assert!(shape == (2, 3) && values == vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+    // big-endian float32 in Fortran order (column by column)
+    let floats: Vec<u8> = [1.0f32, 4.0, 2.0, 5.0, 3.0, 6.0].iter().flat_map(|v| v.to_be_bytes()).collect();
+    let (shape, values) = arrays::from_npy(&npy(">f4", "(2, 3)", &floats)).unwrap();
+    assert!(shape == (2, 3) && values == vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+    assert!(matches!(arrays::from_npy(&npy("<c16", "(1, 1)", &[0; 16])), Err(FormatError::DType(d)) if d == "<c16"));
+    assert!(matches!(arrays::from_npy(&npy("<f8", "(2, 3)", &[0; 40])), Err(FormatError::Truncated)));
+    assert!(matches!(arrays::from_npy(&npy("<f8", "(6,)", &[0; 48])), Err(FormatError::Invalid(_))));
+    assert!(matches!(arrays::from_npy(b"1,2,3\n"), Err(FormatError::WrongKind { .. })));
+
+    // malformed shapes are errors rather than panics
+    assert!(matches!(arrays::from_npy(&npy("<f8", ")(2, 3,", &[0; 48])), Err(FormatError::Invalid(_))));
+    assert!(matches!(arrays::from_npy(&npy("<f8", "2, 3", &[0; 48])), Err(FormatError::Invalid(_))));
+    assert!(matches!(arrays::from_npy(&npy("<f8", "(4294967296, 4294967296)", &[0; 48])), Err(FormatError::Truncated)));
+    assert!(matches!(arrays::from_npy(&npy("<f8", "(18446744073709551615, 2)", &[0; 48])), Err(FormatError::Truncated)));
+}
+
+#[test]
+fn offscreen_render_layers() {
+    let cc = 4;
+    let mut grid = StaggeredMACGrid::new(cc);
+    *grid.temp_grid_mut(1, 2) = 1.0;
+    grid.obstacles.push(Obstacle::rectangle(vector2(3.0, 0.0), vector2(4.0, 1.0)));
+    grid.update_obstacles();
+    let simulator = Simulator::new(grid);
+
+    let mut layers = LayerStack::new();
+    let scale = ColorScale::new(Colormap::Greyscale, Normalization::Auto);
+    layers.temperature = Some(scale.clone());
+    layers.points = vec![(vector2(0.5, 3.5), Color32::RED)];
+
+    let image = layers.render(&simulator, 40);
+    let pixel = |x: usize, y: usize| image.pixels[x + y * 40];
+    assert!(image.size == [40, 40]);
+    // each cell covers 10x10 pixels, rows go down with y
+    assert!(pixel(15, 25) == scale.sample(1.0) && pixel(5, 5) == scale.sample(0.0));
+    // obstacles are drawn translucently on top of the fields
+    assert!(pixel(35, 5) != scale.sample(0.0) && pixel(35, 5).a() == 255);
+    assert!(pixel(5, 35) == Color32::RED);
+
+    layers.obstacles = false;
+    assert!(layers.render(&simulator, 40).pixels[35 + 5 * 40] == scale.sample(0.0));
+}
+
+#[test]
+fn record_png_frames_and_gif() {
+    let dir = std::env::temp_dir().join(format!("flowy-recording-test-{}", std::process::id()));
+    let mut layers = LayerStack::new();
+    layers.dye = Some(ColorScale::new(Colormap::Viridis, Normalization::Auto));
+
+    let mut grid = random_walled_grid(8, 3);
+    *grid.dye_grid_mut(4, 4) = 1.0;
+
+    // every third step of nine
+    let mut recorder = Recorder::new(RecordingFormat::PngFrames, dir.join("frames"), 2);
+    recording::record_headless(&mut Simulator::new(grid.clone()), 0.1, 9, &layers, 32, &mut recorder).unwrap();
+    assert!(recorder.frames() == 3);
+    let decoder = png::Decoder::new(std::fs::File::open(dir.join("frames").join("frame_00002.png")).unwrap());
+    let reader = decoder.read_info().unwrap();
+    assert!(reader.info().width == 32 && reader.info().height == 32);
+    assert!(!dir.join("frames").join("frame_00003.png").exists());
+
+    let mut recorder = Recorder::new(RecordingFormat::Gif, dir.join("run.gif"), 0);
+    recording::record_headless(&mut Simulator::new(grid), 0.1, 4, &layers, 32, &mut recorder).unwrap();
+    let gif = std::fs::read(dir.join("run.gif")).unwrap();
+    assert!(gif.starts_with(b"GIF89a") && gif.ends_with(&[0x3b]));
+    assert!(u16::from_le_bytes([gif[6], gif[7]]) == 32);
+    // one graphic control extension per frame
+    assert!(gif.windows(3).filter(|w| w == &[0x21, 0xf9, 0x04]).count() == 4);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn headless_options() {
+    let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+
+    let options = RunOptions::parse(&[]).unwrap();
+    assert!(options.steps == Some(100) && options.until.is_none() && options.projection.is_none());
+
+    let options = RunOptions::parse(&args("--until 2.5 --dt 0.1 --vtk out --no-projection --print-every 0")).unwrap();
+    assert!(options.steps.is_none() && options.until == Some(2.5) && options.dt == Some(0.1));
+    assert!(options.vtk == Some(std::path::PathBuf::from("out")) && options.projection == Some(false) && options.print_every == 0);
+
+    for invalid in ["--steps", "--steps ten", "--dt -1", "--frobnicate", "--snapshot a --resume b", "--frame-size 2"] {
+        assert!(RunOptions::parse(&args(invalid)).is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn headless_run_stops_on_divergence() {
+    let mut options = RunOptions::parse(&["--until".into(), "1.0".into(), "--print-every".into(), "2".into()]).unwrap();
+    let mut log = Vec::new();
+
+    // runs until the time is reached even if it isn't a multiple of the step
+    let mut simulator = Simulator::new(random_walled_grid(8, 13));
+    let outcome = headless::run(&mut simulator, 0.3, &options, &mut log).unwrap();
+    assert!(outcome == RunOutcome::Completed { steps: 3 });
+    let log = String::from_utf8(log).unwrap();
+    assert!(log.lines().count() == 2 && log.starts_with("step      2  t = 0.6"));
+
+    let mut simulator = Simulator::new(random_walled_grid(8, 13));
+    *simulator.grid.temp_grid_mut(3, 3) = f64::NAN;
+    let outcome = headless::run(&mut simulator, 0.3, &options, &mut Vec::new()).unwrap();
+    assert!(matches!(outcome, RunOutcome::Diverged { step: 1, reason } if reason == "non-finite temperature"));
+
+    options.max_speed = 0.1;
+    let mut simulator = Simulator::new(random_walled_grid(8, 13));
+    assert!(headless::check_health(&simulator, options.max_speed).is_some());
+    *simulator.grid.vel_x_grid_mut(4, 4) = f64::INFINITY;
+    assert!(headless::check_health(&simulator, f64::INFINITY) == Some(String::from("non-finite velocity")));
+}
+
+#[test]
+fn headless_diagnostics_csv() {
+    let dir = std::env::temp_dir().join(format!("flowy-diagnostics-test-{}", std::process::id()));
+    let csv = dir.join("diagnostics.csv");
+    let checkpoint = dir.join("run.flowy");
+    let times = || std::fs::read_to_string(&csv).unwrap().lines().skip(1).map(|l| l.split(',').next().unwrap().parse::<f64>().unwrap()).collect::<Vec<_>>();
+
+    // every step is written even though only the last few are kept in memory
+    let options = RunOptions { steps: Some(10), diagnostics: Some(csv.clone()), checkpoint: Some(checkpoint.clone()), checkpoint_every: 4, quiet: true, ..RunOptions::new() };
+    let mut simulator = Simulator::new(random_walled_grid(8, 13));
+    simulator.diagnostics.capacity = 3;
+    headless::run(&mut simulator, 0.25, &options, &mut Vec::new()).unwrap();
+    assert!(simulator.diagnostics.samples().len() == 3);
+    assert!(times() == (1..=10).map(|i| i as f64 * 0.25).collect::<Vec<_>>());
+    assert!(std::fs::read_to_string(&csv).unwrap().starts_with("time,kinetic_energy,"));
+
+    // resuming from the checkpoint of step 8 replaces the lines of steps 9 and 10
+    let options = RunOptions { steps: Some(4), resume: Some(checkpoint.clone()), ..options };
+    let (mut resumed, dt) = options.initial_state().unwrap();
+    headless::run(&mut resumed, dt, &options, &mut Vec::new()).unwrap();
+    assert!(times() == (1..=12).map(|i| i as f64 * 0.25).collect::<Vec<_>>());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn scenario_files() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios/example.toml");
+    let scenario = Scenario::load(&path).unwrap();
+    assert!(scenario.grid.cells == 32 && scenario.obstacles.len() == 1 && scenario.simulation.until == Some(20.0));
+
+    // TOML and JSON describe the same scenario
+    assert!(Scenario::from_toml(&scenario.to_toml()).unwrap() == scenario);
+    assert!(Scenario::from_json(&scenario.to_json()).unwrap() == scenario);
+
+    let simulator = scenario.build().unwrap();
+    let grid = &simulator.grid;
+    // x velocities are sampled at the face centers (x, y + 0.5)
+    assert!((grid.vel_x_grid(0, 3) - 0.05 * 3.5).abs() < 1e-12 && grid.vel_x_grid(31, 3) == grid.vel_x_grid(0, 3));
+    // the image covers x in [12, 20), the patch is a disk around (6, 16), the obstacle clears the fields
+    assert!(grid.dye_grid(12, 0) == 1.0 && grid.dye_grid(11, 0) == 0.0 && grid.dye_grid(19, 31) == 1.0);
+    assert!(grid.temp_grid(6, 16) == 10.0 && grid.temp_grid(6, 20) == 0.0);
+    assert!(grid.is_solid(16, 16) && grid.vel_x_grid(16, 16) == 0.0);
+    assert!(simulator.particles.emitters.len() == 1 && simulator.probes.probes[0].name == "wake");
+
+    // command line options override the scenario
+    let args = ["--scenario", path.to_str().unwrap(), "--steps", "5", "--npz", "fields"].map(String::from);
+    let options = RunOptions::parse(&args).unwrap();
+    assert!(options.steps == Some(5) && options.until == Some(20.0) && options.output_every == 25);
+    // output paths in the file are relative to it, those on the command line to the working directory
+    assert!(options.npz == Some("fields".into()) && options.vtk == Some(path.parent().unwrap().join("out/vtk")));
+    assert!(options.initial_state().unwrap().1 == 0.2);
+
+    // the setup of a running simulation saves and loads back into the same simulator, minus the fields
+    let dir = std::env::temp_dir().join(format!("flowy-scenario-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut running = scenario.build().unwrap();
+    running.checkpoints.interval = 7;
+    running.checkpoints.path = dir.join("run.flowy");
+    let saved = dir.join("saved.toml");
+    Scenario::from_simulator(&running, 0.3).save(&saved).unwrap();
+    let loaded = Scenario::load(&saved).unwrap();
+    let rebuilt = loaded.build().unwrap();
+    assert!(loaded.simulation.dt == 0.3 && loaded.grid == scenario.grid && loaded.obstacles == scenario.obstacles);
+    assert!(rebuilt.particles.emitters == running.particles.emitters && rebuilt.probes == running.probes && rebuilt.forces == running.forces);
+    assert!(rebuilt.checkpoints == running.checkpoints && rebuilt.projection == running.projection && rebuilt.pressure_solver == running.pressure_solver);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn scenario_errors() {
+    let minimal = Scenario::from_json(r#"{ "grid": { "cells": 8 }, "initial": { "temperature": 2 } }"#).unwrap();
+    assert!(minimal.initial.temperature == Some(FieldInit::Constant(2.0)) && minimal.simulation.dt == 0.2);
+    assert!(minimal.build().unwrap().grid.temp_grid(7, 7) == 2.0);
+
+    for invalid in [
+        "[grid]\ncells = 0",
+        "[grid]\ncels = 8",
+        "[grid]\nboundary = \"open\"",
+        "[simulation]\ndt = -1",
+        "[simulation]\nsolver_iterations = 0",
+        "[initial]\ndye = \"red\"",
+        "[initial]\ndye = { value = 1 }",
+        "[[obstacles]]\nshape = \"circle\"\ncenter = [1, 1]\nradius = 0",
+        "[[patches]]\nfield = \"pressure\"\nvalue = 1\nregion = { shape = \"circle\", center = [1, 1], radius = 1 }",
+        "name = ",
+        "[initial]\ndye = \"sin(x\"",
+        "[initial]\ndye = \"max(x)\"",
+    ] {
+        assert!(matches!(Scenario::from_toml(invalid), Err(FormatError::Invalid(_))), "{invalid}");
+    }
+
+    let infinite = Scenario::from_toml("[initial]\ndye = \"1 / (x - 0.125)\"\n[grid]\ncells = 4").unwrap();
+    assert!(matches!(infinite.build(), Err(FormatError::Invalid(_))));
+
+    let missing_image = Scenario::from_toml("[initial]\ndye = { image = \"missing.png\" }").unwrap();
+    assert!(matches!(missing_image.build(), Err(FormatError::Io(_))));
+}
+
+#[test]
+fn forces_and_sources() {
+    let mut grid = StaggeredMACGrid::new(8);
+    *grid.temp_grid_mut(3, 5) = 2.0;
+    let mut forces = Forces::new();
+    forces.buoyancy = 0.5;
+    forces.ambient_temperature = 0.5;
+    forces.sources.push(Patch { field: GridField::VelocityX, value: 1.0, region: Obstacle::rectangle(vector2(0.0, 0.0), vector2(8.0, 1.0)) });
+    forces.apply(&mut grid, 0.1);
+
+    // faces above and below the hot cell rise (y points down), the others sink with the cooler ambient
+    assert!((grid.vel_y_grid(3, 5) + 0.1 * 0.5 * 0.5).abs() < 1e-12 && (grid.vel_y_grid(3, 6) + 0.1 * 0.5 * 0.5).abs() < 1e-12);
+    assert!((grid.vel_y_grid(0, 4) - 0.1 * 0.5 * 0.5).abs() < 1e-12);
+    // walls keep their normal velocities
+    assert!(grid.vel_y_grid(3, 0) == 0.0 && grid.vel_y_grid(3, 8) == 0.0);
+    // the source covers the faces of the first row, walls included
+    assert!((0..=8).all(|x| grid.vel_x_grid(x, 0) == 1.0) && grid.vel_x_grid(4, 1) == 0.0);
+}
+
+#[test]
+fn builtin_scenarios() {
+    for builtin in BuiltinScenario::ALL {
+        let scenario = builtin.scenario();
+        assert!(scenario.name == builtin.name() && BuiltinScenario::from_key(builtin.key()) == Some(builtin));
+        assert!(Scenario::open(builtin.key()).unwrap() == scenario);
+
+        let mut simulator = scenario.build().unwrap();
+        for _ in 0..3 {
+            simulator.step(scenario.simulation.dt);
+        }
+        assert!(headless::check_health(&simulator, 10.0).is_none(), "{}", builtin.name());
+    }
+
+    let plume = BuiltinScenario::SmokePlume.scenario().build().unwrap();
+    assert!(plume.forces.buoyancy > 0.0 && plume.particles.emitters.len() == 1);
+    let vortex_street = BuiltinScenario::KarmanVortexStreet.scenario().build().unwrap();
+    assert!(vortex_street.grid.is_solid(16, 32) && vortex_street.grid.vel_x_grid(0, 10) == 1.0);
+    assert!(BuiltinScenario::KelvinHelmholtz.scenario().grid.boundary == BoundaryPolicy::Periodic);
+}
+
+#[test]
+fn expressions() {
+    let eval = |source: &str| Expression::parse(source).unwrap().eval(0.25, 0.5, 8.0);
+
+    assert!(eval("1 + 2 * 3 - 4 / 2") == 5.0);
+    assert!(eval("-2^2") == -4.0 && eval("2^3^2") == 512.0 && eval("2 ** -1") == 0.5 && eval("(1 + 2) * 3") == 9.0);
+    assert!(eval("1.5e1 + .5 + 2E-1") == 15.7 && eval("2 * e") == 2.0 * std::f64::consts::E);
+    assert!(eval("x * n + y") == 2.5 && eval(" sin( pi*x )*cos(pi*y) ").abs() < 1e-15);
+    assert!(eval("min(x, y) + max(1, 2) + atan2(0, -1) + step(0.3, x) + sign(-3)") == 0.25 + 2.0 + std::f64::consts::PI + 0.0 - 1.0);
+    assert!((eval("sqrt(abs(-16)) + ln(exp(2)) + log10(100) + tanh(0) + pow(x, 2)") - 8.0625).abs() < 1e-15);
+
+    for (source, position) in [("", 0), ("1 +", 3), ("(x", 2), ("sin x", 4), ("foo(1)", 0), ("pow(1)", 0), ("2 x", 2), ("x $ y", 2)] {
+        let error = Expression::parse(source).unwrap_err();
+        assert!(error.position == position, "{source}: {error}");
+    }
+
+    // hostile nesting is an error instead of a stack overflow, reasonable nesting still parses
+    let deep = 20000;
+    for source in [format!("{}x{}", "(".repeat(deep), ")".repeat(deep)), format!("{}x", "-".repeat(deep)), format!("{}x", "x^".repeat(deep)), format!("{}x", "x+".repeat(deep)), format!("{}x{}", "sin(".repeat(deep), ")".repeat(deep))] {
+        assert!(Expression::parse(&source).unwrap_err().message.contains("nested"));
+    }
+    assert!(eval(&format!("{}x{}", "(".repeat(250), ")".repeat(250))) == 0.25 && eval(&format!("{}1", "1+".repeat(100))) == 101.0);
+
+    // evaluated at the faces and cell centers of each field
+    let mut grid = StaggeredMACGrid::new(4);
+    let x_plus_y = Expression::parse("x + 10 * y").unwrap();
+    for field in GridField::ALL {
+        x_plus_y.fill(&mut grid, field).unwrap();
+    }
+    assert!(grid.vel_x_grid(0, 0) == 10.0 * 0.125 && grid.vel_x_grid(4, 1) == 1.0 + 10.0 * 0.375);
+    assert!(grid.vel_y_grid(1, 0) == 0.375 && grid.vel_y_grid(0, 4) == 0.125 + 10.0);
+    assert!(grid.temp_grid(3, 0) == 0.875 + 1.25 && grid.dye_grid(0, 3) == 0.125 + 8.75);
+
+    // nothing changes if some value isn't finite
+    let before = grid.clone();
+    assert!(Expression::parse("1 / x").unwrap().fill(&mut grid, GridField::VelocityX).is_err());
+    assert!(grid == before);
+}
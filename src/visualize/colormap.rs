@@ -0,0 +1,274 @@
+use egui::{Painter, Ui, DragValue, Align2, FontId};
+use epaint::{Color32, Rect, Rounding, Stroke, pos2, vec2};
+
+/// perceptually uniform and diverging color maps for scalar fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+    Viridis,
+    Magma,
+    Turbo,
+    Coolwarm,
+    Greyscale,
+    Custom
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 6] = [Self::Viridis, Self::Magma, Self::Turbo, Self::Coolwarm, Self::Greyscale, Self::Custom];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Viridis => "viridis",
+            Self::Magma => "magma",
+            Self::Turbo => "turbo",
+            Self::Coolwarm => "coolwarm (diverging)",
+            Self::Greyscale => "greyscale",
+            Self::Custom => "custom gradient"
+        }
+    }
+}
+
+// evenly spaced samples of the matplotlib / Moreland color maps
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84], [71, 44, 122], [59, 81, 139], [44, 113, 142], [33, 144, 141],
+    [39, 173, 129], [92, 200, 99], [170, 220, 50], [253, 231, 37]
+];
+
+const MAGMA: [[u8; 3]; 9] = [
+    [0, 0, 4], [28, 16, 68], [79, 18, 123], [129, 37, 129], [181, 54, 122],
+    [229, 80, 100], [251, 135, 97], [254, 194, 135], [252, 253, 191]
+];
+
+const COOLWARM: [[u8; 3]; 9] = [
+    [59, 76, 192], [98, 130, 234], [141, 176, 254], [184, 208, 249], [221, 221, 221],
+    [245, 196, 173], [244, 154, 123], [222, 96, 77], [180, 4, 38]
+];
+
+/// piecewise linear color gradient through `stops` (positions in [0, 1], ascending)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub stops: Vec<(f32, Color32)>
+}
+
+impl Gradient {
+    fn from_samples(samples: &[[u8; 3]]) -> Self {
+        let n = samples.len() - 1;
+        let stops = samples.iter()
+            .enumerate()
+            .map(|(i, [r, g, b])| (i as f32 / n as f32, Color32::from_rgb(*r, *g, *b)))
+            .collect();
+
+        Self { stops }
+    }
+
+    pub fn sample(&self, t: f32) -> Color32 {
+        let t = t.clamp(0.0, 1.0);
+
+        let Some(upper) = self.stops.iter().position(|(pos, _)| *pos >= t) else {
+            return self.stops.last().map_or(Color32::BLACK, |(_, c)| *c);
+        };
+        if upper == 0 {
+            return self.stops[0].1;
+        }
+
+        let (p0, c0) = self.stops[upper - 1];
+        let (p1, c1) = self.stops[upper];
+        let s = if p1 > p0 { (t - p0) / (p1 - p0) } else { 0.0 };
+        let lerp = |a: u8, b: u8| (a as f32 + s * (b as f32 - a as f32)).round() as u8;
+
+        Color32::from_rgb(lerp(c0.r(), c1.r()), lerp(c0.g(), c1.g()), lerp(c0.b(), c1.b()))
+    }
+
+    /// editor for the stop colors, stops are kept evenly spaced
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            for (_, color) in &mut self.stops {
+                ui.color_edit_button_srgba(color);
+            }
+
+            if ui.small_button("+").clicked() {
+                let last = self.stops.last().map_or(Color32::WHITE, |(_, c)| *c);
+                self.stops.push((1.0, last));
+            }
+            if self.stops.len() > 2 && ui.small_button("-").clicked() {
+                self.stops.pop();
+            }
+        });
+
+        let n = self.stops.len() - 1;
+        for (i, (pos, _)) in self.stops.iter_mut().enumerate() {
+            *pos = i as f32 / n as f32;
+        }
+    }
+}
+
+/// Google's polynomial approximation of the turbo color map
+fn turbo(t: f32) -> Color32 {
+    let t = t.clamp(0.0, 1.0) as f64;
+    let r = 0.13572138 + t * (4.61539260 + t * (-42.66032258 + t * (132.13108234 + t * (-152.94239396 + t * 59.28637943))));
+    let g = 0.09140261 + t * (2.19418839 + t * (4.84296658 + t * (-14.18503333 + t * (4.27729857 + t * 2.82956604))));
+    let b = 0.10667330 + t * (12.64194608 + t * (-60.58204836 + t * (110.36276771 + t * (-89.90310912 + t * 27.34824973))));
+    let channel = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    Color32::from_rgb(channel(r), channel(g), channel(b))
+}
+
+/// how the data range is mapped onto the color map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// minimum and maximum of the current data
+    Auto,
+    /// largest absolute value of the current data, centered at zero (for diverging maps)
+    AutoSymmetric,
+    /// user-defined range
+    Fixed
+}
+
+impl Normalization {
+    pub const ALL: [Normalization; 3] = [Self::Auto, Self::AutoSymmetric, Self::Fixed];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Auto => "automatic",
+            Self::AutoSymmetric => "automatic (symmetric)",
+            Self::Fixed => "fixed"
+        }
+    }
+}
+
+/// maps the values of one scalar view to colors
+#[derive(Clone)]
+pub struct ColorScale {
+    pub colormap: Colormap,
+    pub custom: Gradient,
+    pub normalization: Normalization,
+    pub fixed_min: f64,
+    pub fixed_max: f64,
+
+    viridis: Gradient,
+    magma: Gradient,
+    coolwarm: Gradient,
+    range: (f64, f64)
+}
+
+impl ColorScale {
+    pub fn new(colormap: Colormap, normalization: Normalization) -> Self {
+        Self {
+            colormap,
+            custom: Gradient { stops: vec![(0.0, Color32::BLACK), (0.5, Color32::RED), (1.0, Color32::YELLOW)] },
+            normalization,
+            fixed_min: 0.0,
+            fixed_max: 1.0,
+
+            viridis: Gradient::from_samples(&VIRIDIS),
+            magma: Gradient::from_samples(&MAGMA),
+            coolwarm: Gradient::from_samples(&COOLWARM),
+            range: (0.0, 1.0)
+        }
+    }
+
+    pub fn range(&self) -> (f64, f64) {
+        self.range
+    }
+
+    /// updates the normalization range from the current data (non-finite values are ignored)
+    pub fn update_range(&mut self, values: impl IntoIterator<Item = f64>) {
+        let (min, max) = values.into_iter()
+            .filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| (min.min(v), max.max(v)));
+        let (min, max) = if min <= max { (min, max) } else { (0.0, 0.0) };
+
+        self.range = match self.normalization {
+            Normalization::Auto => (min, max),
+            Normalization::AutoSymmetric => {
+                let extent = min.abs().max(max.abs());
+                (-extent, extent)
+            },
+            Normalization::Fixed => (self.fixed_min, self.fixed_max)
+        };
+    }
+
+    /// `value` mapped to [0, 1] by the current range
+    pub fn normalize(&self, value: f64) -> f32 {
+        let (min, max) = self.range;
+        if max > min { ((value - min) / (max - min)).clamp(0.0, 1.0) as f32 } else { 0.5 }
+    }
+
+    /// color at the normalized position `t`
+    pub fn sample(&self, t: f32) -> Color32 {
+        match self.colormap {
+            Colormap::Viridis => self.viridis.sample(t),
+            Colormap::Magma => self.magma.sample(t),
+            Colormap::Turbo => turbo(t),
+            Colormap::Coolwarm => self.coolwarm.sample(t),
+            Colormap::Greyscale => Color32::from_gray((t.clamp(0.0, 1.0) * 255.0) as u8),
+            Colormap::Custom => self.custom.sample(t)
+        }
+    }
+
+    pub fn color(&self, value: f64) -> Color32 {
+        self.sample(self.normalize(value))
+    }
+
+    /// settings widgets, `id` distinguishes the combo boxes of different scales; true if anything changed
+    pub fn ui(&mut self, ui: &mut Ui, id: &str) -> bool {
+        let before = (self.colormap, self.custom.clone(), self.normalization, self.fixed_min, self.fixed_max);
+
+        egui::ComboBox::from_id_source(format!("{id}_colormap"))
+            .selected_text(self.colormap.name())
+            .show_ui(ui, |ui| {
+                for colormap in Colormap::ALL {
+                    ui.selectable_value(&mut self.colormap, colormap, colormap.name());
+                }
+            });
+
+        if self.colormap == Colormap::Custom {
+            self.custom.ui(ui);
+        }
+
+        egui::ComboBox::from_id_source(format!("{id}_normalization"))
+            .selected_text(self.normalization.name())
+            .show_ui(ui, |ui| {
+                for normalization in Normalization::ALL {
+                    ui.selectable_value(&mut self.normalization, normalization, normalization.name());
+                }
+            });
+
+        if self.normalization == Normalization::Fixed {
+            ui.horizontal(|ui| {
+                ui.label("Range");
+                ui.add(DragValue::new(&mut self.fixed_min).speed(0.01));
+                ui.add(DragValue::new(&mut self.fixed_max).speed(0.01));
+            });
+        }
+
+        before != (self.colormap, self.custom.clone(), self.normalization, self.fixed_min, self.fixed_max)
+    }
+}
+
+/// vertical color bar with `ticks` labelled values filling `rect`, titled `label`
+pub fn draw_color_bar(painter: &Painter, rect: Rect, scale: &ColorScale, label: &str, ticks: usize) {
+    const SEGMENTS: usize = 64;
+
+    let segment_height = rect.height() / SEGMENTS as f32;
+    for i in 0..SEGMENTS {
+        // maximum at the top
+        let t = 1.0 - (i as f32 + 0.5) / SEGMENTS as f32;
+        let min = pos2(rect.min.x, rect.min.y + i as f32 * segment_height);
+        let segment = Rect::from_min_size(min, vec2(rect.width(), segment_height + 0.5));
+        painter.rect_filled(segment, Rounding::ZERO, scale.sample(t));
+    }
+    painter.rect_stroke(rect, Rounding::ZERO, Stroke::new(1.0, Color32::GRAY));
+
+    let font = FontId::proportional(11.0);
+    let (min, max) = scale.range();
+    for i in 0..ticks {
+        let t = i as f32 / (ticks - 1).max(1) as f32;
+        let y = rect.max.y - t * rect.height();
+        let value = min + t as f64 * (max - min);
+
+        painter.line_segment([pos2(rect.min.x - 3.0, y), pos2(rect.min.x, y)], Stroke::new(1.0, Color32::GRAY));
+        painter.text(pos2(rect.min.x - 5.0, y), Align2::RIGHT_CENTER, format!("{value:.3}"), font.clone(), Color32::GRAY);
+    }
+
+    painter.text(pos2(rect.center().x, rect.min.y - 4.0), Align2::CENTER_BOTTOM, label, font, Color32::GRAY);
+}
@@ -0,0 +1,40 @@
+use egui::{Context, Painter, TextureHandle, TextureOptions};
+use epaint::{Color32, ColorImage, Rect, pos2};
+
+use super::colormap::ColorScale;
+
+/// a colormapped scalar field rasterized into a texture, re-uploaded whenever it is updated
+pub struct FieldTexture {
+    name: &'static str,
+    texture: Option<TextureHandle>
+}
+
+impl FieldTexture {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, texture: None }
+    }
+
+    /// `values` are `resolution`² samples, row by row; `smooth` filters linearly between texels
+    pub fn update(&mut self, ctx: &Context, resolution: usize, values: &[f64], scale: &ColorScale, smooth: bool) {
+        let pixels = values.iter().map(|v| scale.color(*v)).collect();
+        let image = ColorImage { size: [resolution, resolution], pixels };
+        self.set_image(ctx, image, smooth);
+    }
+
+    pub fn set_image(&mut self, ctx: &Context, image: ColorImage, smooth: bool) {
+        let options = if smooth { TextureOptions::LINEAR } else { TextureOptions::NEAREST };
+
+        match &mut self.texture {
+            Some(texture) => texture.set(image, options),
+            None => self.texture = Some(ctx.load_texture(self.name, image, options))
+        }
+    }
+
+    /// stretches the texture over `rect` (screen space), `tint` can fade it
+    pub fn draw(&self, painter: &Painter, rect: Rect, tint: Color32) {
+        if let Some(texture) = &self.texture {
+            let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+            painter.image(texture.id(), rect, uv, tint);
+        }
+    }
+}
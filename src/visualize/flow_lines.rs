@@ -0,0 +1,128 @@
+use std::collections::VecDeque;
+
+use crate::simulator::{grid::StaggeredMACGrid, integration::rk4, math::Vector2};
+
+/// seeded integral curves of the velocity field (all positions in world space):
+/// streamlines follow the current field, pathlines the trajectory of a particle released at the seed
+/// and streaklines connect all particles continuously released at the seed
+pub struct FlowLines {
+    seeds: Vec<Vector2>,
+    pathlines: Vec<VecDeque<Vector2>>,
+    streaklines: Vec<VecDeque<Vector2>>,
+
+    /// number of simulation steps kept in pathlines and streaklines
+    pub history_length: usize,
+    /// integration step and maximum number of steps in each direction for streamlines
+    pub streamline_step: f64,
+    pub streamline_max_steps: usize
+}
+
+impl Default for FlowLines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlowLines {
+    pub fn new() -> Self {
+        Self {
+            seeds: Vec::new(),
+            pathlines: Vec::new(),
+            streaklines: Vec::new(),
+            history_length: 200,
+            streamline_step: 0.25,
+            streamline_max_steps: 400
+        }
+    }
+
+    pub fn seeds(&self) -> &[Vector2] {
+        &self.seeds
+    }
+
+    pub fn pathlines(&self) -> &[VecDeque<Vector2>] {
+        &self.pathlines
+    }
+
+    pub fn streaklines(&self) -> &[VecDeque<Vector2>] {
+        &self.streaklines
+    }
+
+    pub fn add_seed(&mut self, pos: Vector2) {
+        self.seeds.push(pos);
+        self.pathlines.push(VecDeque::from([pos]));
+        self.streaklines.push(VecDeque::from([pos]));
+    }
+
+    /// `count` seeds evenly spaced on the segment from `from` to `to`
+    pub fn add_rake(&mut self, from: Vector2, to: Vector2, count: usize) {
+        for i in 0..count {
+            let t = if count > 1 { i as f64 / (count - 1) as f64 } else { 0.5 };
+            self.add_seed(from + t * (to - from));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.seeds.clear();
+        self.pathlines.clear();
+        self.streaklines.clear();
+    }
+
+    /// restart pathlines and streaklines at their seeds, e.g. after the field was replaced
+    pub fn reset_history(&mut self) {
+        for (i, seed) in self.seeds.iter().enumerate() {
+            self.pathlines[i] = VecDeque::from([*seed]);
+            self.streaklines[i] = VecDeque::from([*seed]);
+        }
+    }
+
+    /// advances the time-dependent lines by one simulation step of length `dt` through the field of `grid`
+    pub fn step(&mut self, grid: &StaggeredMACGrid, dt: f64) {
+        for pathline in &mut self.pathlines {
+            let head = *pathline.back().unwrap();
+            pathline.push_back(rk4(grid, head, dt));
+
+            while pathline.len() > self.history_length {
+                pathline.pop_front();
+            }
+        }
+
+        for (streakline, seed) in self.streaklines.iter_mut().zip(&self.seeds) {
+            // newest particle first, so the line runs downstream from the seed
+            for particle in streakline.iter_mut() {
+                *particle = rk4(grid, *particle, dt);
+            }
+            streakline.push_front(*seed);
+            streakline.truncate(self.history_length);
+        }
+    }
+
+    /// streamline through `seed` in the current field of `grid`, integrated backward and forward
+    /// until it leaves the domain, stagnates or reaches the step limit
+    pub fn streamline(&self, grid: &StaggeredMACGrid, seed: Vector2) -> Vec<Vector2> {
+        let integrate = |h: f64| {
+            let cc = grid.cell_count as f64;
+            let mut line = Vec::new();
+            let mut pos = seed;
+
+            for _ in 0..self.streamline_max_steps {
+                let next = rk4(grid, pos, h);
+                let inside = next.x >= 0.0 && next.y >= 0.0 && next.x <= cc && next.y <= cc;
+                if !inside || (next - pos).len() < 1e-9 {
+                    break;
+                }
+
+                line.push(next);
+                pos = next;
+            }
+
+            line
+        };
+
+        let mut line = integrate(-self.streamline_step);
+        line.reverse();
+        line.push(seed);
+        line.extend(integrate(self.streamline_step));
+
+        line
+    }
+}
@@ -0,0 +1,116 @@
+use epaint::{Color32, ColorImage};
+
+use crate::simulator::{grid::StaggeredMACGrid, math::{vector2, Vector2}};
+
+use super::colormap::ColorScale;
+
+/// line integral convolution: white noise smeared along the streamlines of the velocity field
+pub struct Lic {
+    /// texture size in pixels along each axis
+    pub resolution: usize,
+    /// number of integration steps (of one pixel each) in both directions
+    pub kernel_length: usize,
+    pub color_by_speed: bool,
+
+    noise: Vec<f32>,
+    noise_resolution: usize
+}
+
+impl Default for Lic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lic {
+    pub fn new() -> Self {
+        Self {
+            resolution: 256,
+            kernel_length: 12,
+            color_by_speed: true,
+            noise: Vec::new(),
+            noise_resolution: 0
+        }
+    }
+
+    /// white noise in [0, 1), regenerated only when the resolution changes so the image doesn't flicker
+    fn noise(&mut self) -> &[f32] {
+        if self.noise_resolution != self.resolution {
+            let mut state = 0x2545f4914f6cdd1du64;
+            self.noise = (0..self.resolution * self.resolution)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    (state >> 40) as f32 / (1u64 << 24) as f32
+                })
+                .collect();
+            self.noise_resolution = self.resolution;
+        }
+
+        &self.noise
+    }
+
+    /// `speed_scale` colors the image by speed if `color_by_speed` is set
+    pub fn compute(&mut self, grid: &StaggeredMACGrid, speed_scale: &ColorScale) -> ColorImage {
+        let n = self.resolution;
+        let kernel_length = self.kernel_length;
+        let color_by_speed = self.color_by_speed;
+
+        // velocity at every pixel center, sampled once and looked up while integrating
+        let centers = grid.raster_positions(n);
+        let mut velocities = vec![vector2(0.0, 0.0); n * n];
+        grid.vel_batch(&centers, &mut velocities);
+
+        let noise = self.noise();
+
+        // unit direction of the field at a (pixel space) position, none outside or at stagnation points
+        let direction = |p: Vector2| {
+            if p.x < 0.0 || p.y < 0.0 || p.x >= n as f64 || p.y >= n as f64 {
+                return None;
+            }
+
+            let v = velocities[p.x as usize + p.y as usize * n];
+            let len = v.len();
+            (len > 1e-12).then(|| (1.0 / len) * v)
+        };
+
+        let pixels = (0..n * n)
+            .map(|i| {
+                let start = vector2((i % n) as f64 + 0.5, (i / n) as f64 + 0.5);
+                let mut sum = noise[i];
+                let mut count = 1;
+
+                // box kernel along the streamline in both directions (midpoint steps of one pixel)
+                for sign in [1.0, -1.0] {
+                    let mut p = start;
+                    for _ in 0..kernel_length {
+                        let Some(d) = direction(p) else { break };
+                        let Some(d) = direction(p + (sign * 0.5) * d) else { break };
+                        p = p + sign * d;
+
+                        if p.x < 0.0 || p.y < 0.0 || p.x >= n as f64 || p.y >= n as f64 {
+                            break;
+                        }
+
+                        sum += noise[p.x as usize + p.y as usize * n];
+                        count += 1;
+                    }
+                }
+
+                // averaging flattens the noise towards 0.5, stretch it back to a standard deviation of about 0.15
+                let value = ((sum / count as f32 - 0.5) * (count as f32).sqrt() * 0.5 + 0.5).clamp(0.0, 1.0);
+
+                if color_by_speed {
+                    let color = speed_scale.color(velocities[i].len());
+                    let shade = |c: u8| (c as f32 * value) as u8;
+                    Color32::from_rgb(shade(color.r()), shade(color.g()), shade(color.b()))
+                } else {
+                    Color32::from_gray((value * 255.0) as u8)
+                }
+            })
+            .collect();
+
+        ColorImage { size: [n, n], pixels }
+    }
+}
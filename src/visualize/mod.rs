@@ -1,23 +1,111 @@
 use std::time::Duration;
 
-use chrono::{DateTime, TimeZone, Local, DurationRound, NaiveTime};
+use chrono::Local;
 use eframe::egui;
-use egui::{Painter, Sense, Slider, Grid, DragValue};
-use epaint::{Color32, Rounding, pos2, Vec2, emath::RectTransform, Rect, Stroke, vec2, Hsva};
+use egui::{PointerButton, Painter, Sense, Slider, Response, TextureHandle, TextureOptions};
+use epaint::{Color32, ColorImage, pos2, Pos2, Vec2, emath::RectTransform, Rect, Stroke, vec2, Shape};
 
-use crate::simulator::{math::vector2, simulator::Simulator, grid::StaggeredMACGrid};
+use crate::simulator::{math::{vector2, Vector2}, simulator::Simulator, particles::Emitter, grid::{StaggeredMACGrid, BoundaryPolicy}, interpolation::Interpolation2DKind, derived::DerivedField, brush::Brush, obstacles::Obstacle, diagnostics::DiagnosticSeries, probes::ProbeQuantity, vtk::{self, VtkSeries}, arrays::{self, GridField}, scenario::{Scenario, BuiltinScenario}, expression::Expression, io::FormatError, snapshot::{self, Snapshot}};
 
-#[derive(PartialEq)]
-struct Snapshot {
-    timestep: u32,
-    grid: StaggeredMACGrid
+use self::{colormap::{ColorScale, Colormap, Normalization, draw_color_bar}, field_texture::FieldTexture, flow_lines::FlowLines, lic::Lic, recording::{Recorder, RecordingFormat}, render::LayerStack};
+
+pub mod colormap;
+mod field_texture;
+pub mod flow_lines;
+pub mod lic;
+mod plot;
+pub mod recording;
+pub mod render;
+
+/// upper bound on the side length of upsampled field textures
+const MAX_FIELD_RESOLUTION: usize = 2048;
+
+/// the whole domain with a small margin, in canvas coordinates
+const DEFAULT_VIEW: Rect = Rect { min: pos2(-0.02, -0.02), max: pos2(1.02, 1.02) };
+
+/// what clicking and dragging on the simulation canvas does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CanvasTool {
+    /// a single flow line seed per click
+    Seed,
+    /// a line of evenly spaced flow line seeds per drag
+    Rake,
+    /// a cluster of tracer particles per click
+    Particles,
+    /// a particle emitter per click
+    Emitter,
+    /// a named probe per click
+    Probe,
+    /// injects velocity along the drag direction
+    PaintVelocity,
+    PaintDye,
+    PaintTemperature,
+    /// fades velocity, dye and temperature towards zero
+    Erase,
+    /// obstacles spanned by a drag
+    ObstacleRectangle,
+    ObstacleCircle,
+    /// freehand outline along a drag
+    ObstaclePolygon,
+    /// select, move (drag) and resize (drag the corner handle) obstacles
+    EditObstacles
 }
 
-impl Snapshot {
-    fn new(timestep: u32, grid: StaggeredMACGrid) -> Self {
-        Self {
-            timestep,
-            grid
+impl CanvasTool {
+    const ALL: [CanvasTool; 13] = [
+        Self::Seed, Self::Rake, Self::Particles, Self::Emitter, Self::Probe,
+        Self::PaintVelocity, Self::PaintDye, Self::PaintTemperature, Self::Erase,
+        Self::ObstacleRectangle, Self::ObstacleCircle, Self::ObstaclePolygon, Self::EditObstacles
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Seed => "flow line seed",
+            Self::Rake => "flow line rake",
+            Self::Particles => "tracer particles",
+            Self::Emitter => "particle emitter",
+            Self::Probe => "probe",
+            Self::PaintVelocity => "paint velocity",
+            Self::PaintDye => "paint dye",
+            Self::PaintTemperature => "paint temperature",
+            Self::Erase => "erase",
+            Self::ObstacleRectangle => "obstacle rectangle",
+            Self::ObstacleCircle => "obstacle circle",
+            Self::ObstaclePolygon => "obstacle polygon",
+            Self::EditObstacles => "edit obstacles"
+        }
+    }
+
+    fn is_brush(&self) -> bool {
+        matches!(self, Self::PaintVelocity | Self::PaintDye | Self::PaintTemperature | Self::Erase)
+    }
+}
+
+/// what the current drag in the obstacle editor does to the selected obstacle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ObstacleDrag {
+    Move,
+    Resize
+}
+
+/// how tracer particles and their trails are colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParticleColoring {
+    Fixed,
+    Speed,
+    Temperature,
+    Dye
+}
+
+impl ParticleColoring {
+    const ALL: [ParticleColoring; 4] = [Self::Fixed, Self::Speed, Self::Temperature, Self::Dye];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Fixed => "fixed",
+            Self::Speed => "speed",
+            Self::Temperature => "temperature",
+            Self::Dye => "dye"
         }
     }
 }
@@ -28,46 +116,198 @@ pub struct FlowyApp {
     // visualization parameters
     line_width: f32,
     vel_scaling_factor: f32,
-    temp_scaling_factor: f32,
     ticks_per_second: u32,
 
     draw_grid: bool,
     draw_velocity_edge_vectors: bool,
     draw_velocity_center_vectors: bool,
-    draw_velocity_greyscale: bool,
+    draw_speed: bool,
     draw_temperature: bool,
+    draw_dye: bool,
+    draw_derived: bool,
+    derived_field: DerivedField,
+    draw_streamlines: bool,
+    draw_pathlines: bool,
+    draw_streaklines: bool,
+    draw_lic: bool,
+
+    // color maps of the scalar views, ranges are updated every frame
+    speed_scale: ColorScale,
+    temperature_scale: ColorScale,
+    dye_scale: ColorScale,
+    derived_scale: ColorScale,
+
+    // scalar fields are rasterized into textures every frame
+    speed_texture: FieldTexture,
+    temperature_texture: FieldTexture,
+    dye_texture: FieldTexture,
+    derived_texture: FieldTexture,
+    smooth_fields: bool,
+    upsample_fields: bool,
+    upsampling_factor: usize,
+
+    // line integral convolution, recomputed when the field or its parameters change
+    lic: Lic,
+    lic_texture: Option<TextureHandle>,
+    lic_dirty: bool,
+
+    // flow line seeding
+    flow_lines: FlowLines,
+    canvas_tool: CanvasTool,
+    rake_seed_count: usize,
+    rake_start: Option<Vector2>,
+    brush: Brush,
+
+    // obstacle editing, the draft holds the points of the obstacle being drawn
+    draw_obstacles: bool,
+    obstacle_draft: Vec<Vector2>,
+    selected_obstacle: Option<usize>,
+    obstacle_drag: Option<ObstacleDrag>,
+    obstacle_texture: FieldTexture,
+
+    // tracer particles
+    draw_particles: bool,
+    particle_coloring: ParticleColoring,
+    particles_per_click: usize,
+    emitter_rate: f64,
+
+    // visible part of the canvas ([0, 1]² is the domain) and the hover inspector
+    view: Rect,
+    inspect_cells: bool,
 
     // simulation parameters
     dt: f64,
     simulation_running: bool,
 
+    // diagnostics panel
+    plotted_diagnostics: Vec<DiagnosticSeries>,
+    probe_quantity: ProbeQuantity,
+    export_path: String,
+    probe_export_path: String,
+    vtk_path: String,
+    vtk_series_directory: String,
+    vtk_series: Option<VtkSeries>,
+    array_field: GridField,
+    array_path: String,
+    npz_path: String,
+    export_status: String,
+
+    // offscreen recording of the layer stack
+    recording_format: RecordingFormat,
+    recording_path: String,
+    recording_size: usize,
+    recording_skip: u32,
+    recorder: Option<Recorder>,
+
     snapshots: Vec<Snapshot>,
-    selected_snapshot: Option<usize>
+    selected_snapshot: Option<usize>,
+    snapshot_path: String,
+    snapshot_status: String,
+    checkpoint_path: String,
+    checkpoint_status: String,
+    builtin_scenario: BuiltinScenario,
+    scenario_path: String,
+    scenario_status: String,
+    expression_field: GridField,
+    expression: String,
+    expression_status: String
 }
 
 impl FlowyApp {
     pub fn new(simulator: Simulator) -> Self {
-        let initial_grid = simulator.grid.clone();
-
-        Self {
+        let mut app = Self {
             simulator,
             line_width: 0.5,
             vel_scaling_factor: 1.0,
-            temp_scaling_factor: 1.0,
             ticks_per_second: 16,
 
             draw_grid: false,
             draw_velocity_edge_vectors: false,
             draw_velocity_center_vectors: true,
-            draw_velocity_greyscale: true,
+            // the scalar layers are opaque textures, the one drawn last hides the others
+            draw_speed: false,
             draw_temperature: true,
+            draw_dye: false,
+            draw_derived: false,
+            derived_field: DerivedField::Vorticity,
+            draw_streamlines: true,
+            draw_pathlines: false,
+            draw_streaklines: false,
+            draw_lic: false,
+
+            speed_scale: ColorScale::new(Colormap::Viridis, Normalization::Auto),
+            temperature_scale: ColorScale::new(Colormap::Magma, Normalization::Auto),
+            dye_scale: ColorScale::new(Colormap::Greyscale, Normalization::Auto),
+            derived_scale: ColorScale::new(Colormap::Coolwarm, Normalization::AutoSymmetric),
+
+            speed_texture: FieldTexture::new("speed"),
+            temperature_texture: FieldTexture::new("temperature"),
+            dye_texture: FieldTexture::new("dye"),
+            derived_texture: FieldTexture::new("derived"),
+            smooth_fields: true,
+            upsample_fields: false,
+            upsampling_factor: 4,
+
+            lic: Lic::new(),
+            lic_texture: None,
+            lic_dirty: true,
+
+            flow_lines: FlowLines::new(),
+            canvas_tool: CanvasTool::Seed,
+            rake_seed_count: 10,
+            rake_start: None,
+            brush: Brush::new(2.0, 2.0),
+
+            draw_obstacles: true,
+            obstacle_draft: Vec::new(),
+            selected_obstacle: None,
+            obstacle_drag: None,
+            obstacle_texture: FieldTexture::new("obstacles"),
+
+            draw_particles: true,
+            particle_coloring: ParticleColoring::Speed,
+            particles_per_click: 50,
+            emitter_rate: 20.0,
+
+            view: DEFAULT_VIEW,
+            inspect_cells: true,
 
             dt: 0.2,
             simulation_running: false,
 
-            snapshots: vec![Snapshot::new(0, initial_grid)],
-            selected_snapshot: None
-        }
+            plotted_diagnostics: vec![DiagnosticSeries::KineticEnergy, DiagnosticSeries::MaxDivergence, DiagnosticSeries::StepTime],
+            probe_quantity: ProbeQuantity::Speed,
+            export_path: String::from("diagnostics.csv"),
+            probe_export_path: String::from("probes.csv"),
+            vtk_path: String::from("grid.vti"),
+            vtk_series_directory: String::from("vtk"),
+            vtk_series: None,
+            array_field: GridField::Temperature,
+            array_path: String::from("temperature.csv"),
+            npz_path: String::from("fields.npz"),
+            recording_format: RecordingFormat::Gif,
+            recording_path: String::from("flowy.gif"),
+            recording_size: 512,
+            recording_skip: 0,
+            recorder: None,
+            export_status: String::new(),
+
+            snapshots: Vec::new(),
+            selected_snapshot: None,
+            snapshot_path: String::from("snapshot.flowy"),
+            snapshot_status: String::new(),
+            checkpoint_path: String::from("checkpoint.flowy"),
+            checkpoint_status: String::new(),
+            builtin_scenario: BuiltinScenario::LidDrivenCavity,
+            scenario_path: String::from("scenario.toml"),
+            scenario_status: String::new(),
+            expression_field: GridField::Temperature,
+            expression: String::from("sin(pi*x) * cos(pi*y)"),
+            expression_status: String::new()
+        };
+
+        app.snapshots.push(Snapshot::new(&app.simulator, app.dt));
+        app
     }
 
     fn draw_grid_lines(&self, painter: &Painter, to_screen: &RectTransform) {
@@ -87,23 +327,42 @@ impl FlowyApp {
         }
     }
 
-    fn draw_grid_velocities_greyscale(&self, painter: &Painter, to_screen: &RectTransform) {
-        let cc = self.simulator.grid.cell_count;
+    /// normalizes the color scales to the current fields
+    fn update_color_ranges(&mut self) {
+        let grid = &self.simulator.grid;
 
-        let rect_size = vec2(1.0 / cc as f32, 1.0 / cc as f32);
+        self.speed_scale.update_range(grid.cell_centered_velocities().iter().map(|v| v.len()));
+        self.temperature_scale.update_range(grid.cell_temperatures());
+        self.dye_scale.update_range(grid.cell_dye());
+    }
 
-        for y in 0..cc {
-            for x in 0..cc {
-                let vx = (self.simulator.grid.vel_x_grid(x, y) + self.simulator.grid.vel_x_grid(x + 1, y)) / 2.0;
-                let vy = (self.simulator.grid.vel_y_grid(x, y) + self.simulator.grid.vel_y_grid(x, y + 1)) / 2.0;
-                let len = (vector2(vx, vy).len_squared() / 2.0f64.sqrt()) as f32 * self.vel_scaling_factor;
-                let color = Color32::from_gray((len * 255 as f32) as u8);
+    /// side length of the scalar field textures: one texel per cell, or several sampled through the
+    /// grid interpolation when upsampling
+    fn field_resolution(&self) -> usize {
+        let cc = self.simulator.grid.cell_count as usize;
+        if self.upsample_fields { (cc * self.upsampling_factor).min(MAX_FIELD_RESOLUTION).max(cc) } else { cc }
+    }
 
-                let rect = Rect::from_min_size(pos2(x as f32 / cc as f32, y as f32 / cc as f32), rect_size);
-                let rect_screencoords = to_screen.transform_rect(rect);
-                painter.rect_filled(rect_screencoords, Rounding::ZERO, color);
-            }
-        }
+    fn draw_field_texture(&self, painter: &Painter, to_screen: &RectTransform, texture: &FieldTexture) {
+        let rect = to_screen.transform_rect(Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)));
+        texture.draw(painter, rect, Color32::WHITE);
+    }
+
+    fn draw_grid_speed(&mut self, ctx: &egui::Context, painter: &Painter, to_screen: &RectTransform) {
+        let grid = &self.simulator.grid;
+        let resolution = self.field_resolution();
+
+        let speeds: Vec<f64> = if resolution == grid.cell_count as usize {
+            grid.cell_centered_velocities().iter().map(|v| v.len()).collect()
+        } else {
+            let positions = grid.raster_positions(resolution);
+            let mut velocities = vec![vector2(0.0, 0.0); positions.len()];
+            grid.vel_batch(&positions, &mut velocities);
+            velocities.iter().map(|v| v.len()).collect()
+        };
+
+        self.speed_texture.update(ctx, resolution, &speeds, &self.speed_scale, self.smooth_fields);
+        self.draw_field_texture(painter, to_screen, &self.speed_texture);
     }
 
     fn draw_grid_velocities_edge_vectors(&self, painter: &Painter, to_screen: &RectTransform) {
@@ -134,10 +393,11 @@ impl FlowyApp {
     fn draw_grid_velocities_center_vectors(&self, painter: &Painter, to_screen: &RectTransform) {
         let cc = self.simulator.grid.cell_count;
         let half_grid = 1.0 / (2.0 * cc as f32);
+        let velocities = self.simulator.grid.cell_centered_velocities();
 
         for x in 0..cc {
             for y in 0..cc {
-                let vel = self.simulator.grid.vel(vector2(x as f64, y as f64));
+                let vel = velocities[(x + y * cc) as usize];
                 let vel_scaled = (1.0 / (cc + 1) as f64) * self.vel_scaling_factor as f64 * vel;
 
                 let minx = pos2(x as f32 / cc as f32 + half_grid, y as f32 / cc as f32 + half_grid);
@@ -149,47 +409,1035 @@ impl FlowyApp {
         }
     }
 
-    fn draw_grid_temperature(&self, painter: &Painter, to_screen: &RectTransform) {
-        let cc = self.simulator.grid.cell_count;
-        let half_grid = 1.0 / (2.0 * cc as f32);
+    /// a cell-centered scalar at the field resolution: the cell values, or sampled with `batch` when upsampling
+    fn raster_scalar(&self, cells: impl FnOnce(&StaggeredMACGrid) -> Vec<f64>, batch: impl FnOnce(&StaggeredMACGrid, &[Vector2], &mut [f64])) -> (usize, Vec<f64>) {
+        let grid = &self.simulator.grid;
+        let resolution = self.field_resolution();
 
-        for x in 0..cc {
-            for y in 0..cc {
-                let temp = self.simulator.grid.temp(vector2(x as f64 + 0.5, y as f64 + 0.5));
-                let temp_scaled = temp * self.temp_scaling_factor as f64;
+        if resolution == grid.cell_count as usize {
+            return (resolution, cells(grid));
+        }
+
+        let positions = grid.raster_positions(resolution);
+        let mut values = vec![0.0; positions.len()];
+        batch(grid, &positions, &mut values);
+        (resolution, values)
+    }
+
+    fn draw_grid_temperature(&mut self, ctx: &egui::Context, painter: &Painter, to_screen: &RectTransform) {
+        let (resolution, temperatures) = self.raster_scalar(StaggeredMACGrid::cell_temperatures, StaggeredMACGrid::temp_batch);
+        self.temperature_texture.update(ctx, resolution, &temperatures, &self.temperature_scale, self.smooth_fields);
+        self.draw_field_texture(painter, to_screen, &self.temperature_texture);
+    }
+
+    fn draw_grid_dye(&mut self, ctx: &egui::Context, painter: &Painter, to_screen: &RectTransform) {
+        let (resolution, dye) = self.raster_scalar(StaggeredMACGrid::cell_dye, StaggeredMACGrid::dye_batch);
+        self.dye_texture.update(ctx, resolution, &dye, &self.dye_scale, self.smooth_fields);
+        self.draw_field_texture(painter, to_screen, &self.dye_texture);
+    }
 
-                let center = pos2(x as f32 / cc as f32 + half_grid, y as f32 / cc as f32 + half_grid);
-                let centert = to_screen.transform_pos(center);
+    /// derived fields are only available per cell
+    fn draw_grid_derived(&mut self, ctx: &egui::Context, painter: &Painter, to_screen: &RectTransform) {
+        let values = self.derived_field.compute(&self.simulator.grid, &self.simulator.pressure);
+        self.derived_scale.update_range(values.iter().copied());
 
-                painter.circle_filled(centert, 5.0, Hsva::new(0.0, temp_scaled as f32, 1.0, 1.0));
+        let resolution = self.simulator.grid.cell_count as usize;
+        self.derived_texture.update(ctx, resolution, &values, &self.derived_scale, self.smooth_fields);
+        self.draw_field_texture(painter, to_screen, &self.derived_texture);
+    }
+
+    /// solid cells, obstacle outlines, the selection and the obstacle being drawn
+    fn draw_grid_obstacles(&mut self, ctx: &egui::Context, painter: &Painter, to_screen: &RectTransform) {
+        let grid = &self.simulator.grid;
+        let cc = grid.cell_count;
+
+        let solid = Color32::from_rgba_unmultiplied(90, 90, 90, 230);
+        let pixels = (0..cc * cc).map(|i| if grid.is_solid(i % cc, i / cc) { solid } else { Color32::TRANSPARENT }).collect();
+        self.obstacle_texture.set_image(ctx, ColorImage { size: [cc as usize, cc as usize], pixels }, false);
+        self.draw_field_texture(painter, to_screen, &self.obstacle_texture);
+
+        let stroke = Stroke::new(self.line_width * 2.0, Color32::LIGHT_GRAY);
+        for (i, obstacle) in grid.obstacles.iter().enumerate() {
+            let outline = match obstacle {
+                Obstacle::Rectangle { min, max } => vec![*min, vector2(max.x, min.y), *max, vector2(min.x, max.y)],
+                Obstacle::Circle { center, radius } => (0..64)
+                    .map(|k| k as f64 / 64.0 * std::f64::consts::TAU)
+                    .map(|phi| *center + *radius * vector2(phi.cos(), phi.sin()))
+                    .collect(),
+                Obstacle::Polygon { points } => points.clone()
+            };
+            let points = outline.iter().map(|p| self.world_to_screen(to_screen, *p)).collect();
+            painter.add(Shape::closed_line(points, stroke));
+
+            if self.selected_obstacle == Some(i) {
+                let (min, max) = obstacle.bounds();
+                let rect = Rect::from_two_pos(self.world_to_screen(to_screen, min), self.world_to_screen(to_screen, max));
+                painter.rect_stroke(rect, 0.0, Stroke::new(1.0, Color32::YELLOW));
+                painter.rect_filled(Rect::from_center_size(rect.max, vec2(8.0, 8.0)), 0.0, Color32::YELLOW);
             }
         }
+
+        if let Some(draft) = self.draft_obstacle() {
+            if let Obstacle::Polygon { points } = &draft {
+                self.draw_polyline(painter, to_screen, points.iter(), Color32::YELLOW);
+            } else {
+                let (min, max) = draft.bounds();
+                let rect = Rect::from_two_pos(self.world_to_screen(to_screen, min), self.world_to_screen(to_screen, max));
+                match draft {
+                    Obstacle::Circle { .. } => painter.circle_stroke(rect.center(), rect.width() / 2.0, Stroke::new(1.0, Color32::YELLOW)),
+                    _ => painter.rect_stroke(rect, 0.0, Stroke::new(1.0, Color32::YELLOW))
+                }
+            }
+        }
+    }
+
+    /// the obstacle the current drag of a drawing tool would create
+    fn draft_obstacle(&self) -> Option<Obstacle> {
+        let (first, last) = (*self.obstacle_draft.first()?, *self.obstacle_draft.last()?);
+
+        match self.canvas_tool {
+            CanvasTool::ObstacleRectangle => Some(Obstacle::rectangle(first, last)),
+            CanvasTool::ObstacleCircle => Some(Obstacle::Circle { center: first, radius: (last - first).len() }),
+            CanvasTool::ObstaclePolygon if self.obstacle_draft.len() >= 3 => Some(Obstacle::Polygon { points: self.obstacle_draft.clone() }),
+            _ => None
+        }
+    }
+
+    fn obstacles_changed(&mut self) {
+        self.simulator.grid.update_obstacles();
+        self.lic_dirty = true;
+    }
+
+    fn delete_selected_obstacle(&mut self) {
+        if let Some(i) = self.selected_obstacle.take().filter(|i| *i < self.simulator.grid.obstacles.len()) {
+            self.simulator.grid.obstacles.remove(i);
+            self.obstacles_changed();
+        }
+    }
+
+    fn handle_obstacle_input(&mut self, response: &Response, to_screen: &RectTransform) {
+        let Some(pointer) = response.interact_pointer_pos().or(response.hover_pos()) else { return };
+        let pos = self.screen_to_world(to_screen, pointer);
+
+        if self.canvas_tool != CanvasTool::EditObstacles {
+            if response.drag_started() {
+                self.obstacle_draft = vec![pos];
+            } else if response.dragged() {
+                // freehand outlines keep points about a quarter cell apart, the shapes only need the current end
+                match self.canvas_tool {
+                    CanvasTool::ObstaclePolygon => {
+                        if self.obstacle_draft.last().is_none_or(|last| (pos - *last).len() > 0.25) {
+                            self.obstacle_draft.push(pos);
+                        }
+                    },
+                    _ => {
+                        self.obstacle_draft.truncate(1);
+                        self.obstacle_draft.push(pos);
+                    }
+                }
+            } else if response.drag_released() {
+                if let Some(obstacle) = self.draft_obstacle() {
+                    self.simulator.grid.obstacles.push(obstacle);
+                    self.selected_obstacle = Some(self.simulator.grid.obstacles.len() - 1);
+                    self.obstacles_changed();
+                }
+                self.obstacle_draft.clear();
+            }
+            return;
+        }
+
+        let obstacle_under_pointer = self.simulator.grid.obstacles.iter().rposition(|o| o.contains(pos));
+
+        if response.clicked() {
+            self.selected_obstacle = obstacle_under_pointer;
+        }
+
+        if response.drag_started() {
+            let on_handle = self.selected_obstacle.and_then(|i| self.simulator.grid.obstacles.get(i)).is_some_and(|o| {
+                let (_, max) = o.bounds();
+                (self.world_to_screen(to_screen, max) - pointer).length() < 8.0
+            });
+
+            self.obstacle_drag = if on_handle {
+                Some(ObstacleDrag::Resize)
+            } else {
+                self.selected_obstacle = obstacle_under_pointer;
+                obstacle_under_pointer.map(|_| ObstacleDrag::Move)
+            };
+        } else if response.drag_released() {
+            self.obstacle_drag = None;
+        }
+
+        if let (Some(drag), Some(i), true) = (self.obstacle_drag, self.selected_obstacle, response.dragged()) {
+            let previous = self.screen_to_world(to_screen, pointer - response.drag_delta());
+            let Some(obstacle) = self.simulator.grid.obstacles.get_mut(i) else { return };
+            match drag {
+                ObstacleDrag::Move => obstacle.translate(pos - previous),
+                ObstacleDrag::Resize => {
+                    let (min, _) = obstacle.bounds();
+                    obstacle.resize(min, vector2(pos.x.max(min.x + 0.5), pos.y.max(min.y + 0.5)));
+                }
+            }
+            self.obstacles_changed();
+        }
+
+        if response.ctx.input(|i| i.key_pressed(egui::Key::Delete)) {
+            self.delete_selected_obstacle();
+        }
+    }
+
+    fn draw_grid_lic(&mut self, ctx: &egui::Context, painter: &Painter, to_screen: &RectTransform) {
+        if self.lic_dirty || self.lic_texture.is_none() {
+            let image = self.lic.compute(&self.simulator.grid, &self.speed_scale);
+            match &mut self.lic_texture {
+                Some(texture) => texture.set(image, TextureOptions::LINEAR),
+                None => self.lic_texture = Some(ctx.load_texture("lic", image, TextureOptions::LINEAR))
+            }
+            self.lic_dirty = false;
+        }
+
+        if let Some(texture) = &self.lic_texture {
+            let rect = to_screen.transform_rect(Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)));
+            let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+            painter.image(texture.id(), rect, uv, Color32::WHITE);
+        }
+    }
+
+    fn world_to_screen(&self, to_screen: &RectTransform, pos: Vector2) -> Pos2 {
+        let cc = self.simulator.grid.cell_count as f64;
+        to_screen.transform_pos(pos2((pos.x / cc) as f32, (pos.y / cc) as f32))
+    }
+
+    fn screen_to_world(&self, to_screen: &RectTransform, pos: Pos2) -> Vector2 {
+        let cc = self.simulator.grid.cell_count as f64;
+        let relative = to_screen.inverse().transform_pos(pos);
+        vector2(relative.x as f64 * cc, relative.y as f64 * cc)
+    }
+
+    fn draw_polyline<'a>(&self, painter: &Painter, to_screen: &RectTransform, line: impl IntoIterator<Item = &'a Vector2>, color: Color32) {
+        let points = line.into_iter().map(|p| self.world_to_screen(to_screen, *p)).collect();
+        painter.add(Shape::line(points, Stroke::new(self.line_width * 2.0, color)));
+    }
+
+    fn draw_flow_lines(&self, painter: &Painter, to_screen: &RectTransform) {
+        if self.draw_streamlines {
+            for seed in self.flow_lines.seeds() {
+                let line = self.flow_lines.streamline(&self.simulator.grid, *seed);
+                self.draw_polyline(painter, to_screen, &line, Color32::WHITE);
+            }
+        }
+
+        if self.draw_pathlines {
+            for line in self.flow_lines.pathlines() {
+                self.draw_polyline(painter, to_screen, line, Color32::YELLOW);
+            }
+        }
+
+        if self.draw_streaklines {
+            for line in self.flow_lines.streaklines() {
+                self.draw_polyline(painter, to_screen, line, Color32::LIGHT_BLUE);
+            }
+        }
+
+        for seed in self.flow_lines.seeds() {
+            painter.circle_filled(self.world_to_screen(to_screen, *seed), 3.0, Color32::RED);
+        }
+    }
+
+    fn particle_color(&self, pos: Vector2) -> Color32 {
+        match self.particle_coloring {
+            ParticleColoring::Fixed => Color32::from_rgb(255, 140, 0),
+            ParticleColoring::Speed => self.speed_scale.color(self.simulator.grid.vel(pos).len()),
+            ParticleColoring::Temperature => self.temperature_scale.color(self.simulator.grid.temp(pos)),
+            ParticleColoring::Dye => self.dye_scale.color(self.simulator.grid.dye(pos))
+        }
+    }
+
+    /// a color bar for every visible scalar view, stacked from the right edge of the canvas
+    fn draw_color_bars(&self, painter: &Painter, canvas: Rect) {
+        let speed_visible = self.draw_speed
+            || (self.draw_lic && self.lic.color_by_speed)
+            || (self.draw_particles && self.particle_coloring == ParticleColoring::Speed);
+        let temperature_visible = self.draw_temperature
+            || (self.draw_particles && self.particle_coloring == ParticleColoring::Temperature);
+
+        let bars = [
+            (speed_visible, &self.speed_scale, "speed"),
+            (temperature_visible, &self.temperature_scale, "temperature"),
+            (self.draw_dye || (self.draw_particles && self.particle_coloring == ParticleColoring::Dye), &self.dye_scale, "dye"),
+            (self.draw_derived, &self.derived_scale, self.derived_field.name())
+        ];
+        let height = canvas.height() * 0.4;
+        let mut right = canvas.max.x - 10.0;
+
+        for (_, scale, label) in bars.into_iter().filter(|(visible, _, _)| *visible) {
+            let rect = Rect::from_min_max(pos2(right - 16.0, canvas.center().y - height / 2.0), pos2(right, canvas.center().y + height / 2.0));
+            draw_color_bar(painter, rect, scale, label, 5);
+            // room for the tick labels
+            right -= 90.0;
+        }
+    }
+
+    fn draw_particles(&self, painter: &Painter, to_screen: &RectTransform) {
+        let particles = &self.simulator.particles;
+
+        for particle in &particles.particles {
+            let color = self.particle_color(particle.pos);
+
+            // trail segments fade out towards the oldest position
+            let mut previous = self.world_to_screen(to_screen, particle.pos);
+            for (i, pos) in particle.trail.iter().rev().enumerate() {
+                let alpha = 1.0 - (i + 1) as f32 / (particles.trail_length + 1) as f32;
+                let current = self.world_to_screen(to_screen, *pos);
+                painter.line_segment([previous, current], Stroke::new(self.line_width * 2.0, color.gamma_multiply(alpha)));
+                previous = current;
+            }
+
+            painter.circle_filled(self.world_to_screen(to_screen, particle.pos), 1.5, color);
+        }
+
+        for emitter in &particles.emitters {
+            let center = self.world_to_screen(to_screen, emitter.pos);
+            let radius = (to_screen.scale().x * (emitter.radius / self.simulator.grid.cell_count as f64) as f32).max(3.0);
+            painter.circle_stroke(center, radius, Stroke::new(self.line_width * 2.0, Color32::from_rgb(255, 140, 0)));
+        }
+    }
+
+    /// mouse-wheel zoom around the pointer and panning with the secondary or middle mouse button
+    fn handle_view_input(&mut self, response: &Response) {
+        let to_canvas = RectTransform::from_to(response.rect, self.view);
+
+        if let Some(pointer) = response.hover_pos() {
+            let scroll = response.ctx.input(|i| i.scroll_delta.y);
+            let zoom = (scroll * 0.002).exp() * response.ctx.input(|i| i.zoom_delta());
+
+            if zoom != 1.0 {
+                let anchor = to_canvas.transform_pos(pointer);
+                let min = anchor + (self.view.min - anchor) / zoom;
+                let max = anchor + (self.view.max - anchor) / zoom;
+                self.view = Rect::from_min_max(min, max);
+            }
+        }
+
+        if response.dragged_by(PointerButton::Secondary) || response.dragged_by(PointerButton::Middle) {
+            let delta = response.drag_delta() * self.view.size() / response.rect.size();
+            self.view = self.view.translate(-delta);
+        }
+    }
+
+    /// tooltip with the raw and interpolated values under the pointer
+    fn show_cell_inspector(&self, response: &Response, to_screen: &RectTransform) {
+        let Some(pointer) = response.hover_pos() else { return };
+        let grid = &self.simulator.grid;
+        let pos = self.screen_to_world(to_screen, pointer);
+        let (x, y) = (pos.x.floor() as i32, pos.y.floor() as i32);
+
+        if !(0..grid.cell_count).contains(&x) || !(0..grid.cell_count).contains(&y) {
+            return;
+        }
+
+        response.clone().on_hover_ui_at_pointer(|ui| {
+            ui.label(format!("cell ({x}, {y}){}", if grid.is_solid(x, y) { ", solid" } else { "" }));
+            ui.label(format!("position ({:.3}, {:.3})", pos.x, pos.y));
+            ui.label(format!("x faces: {:.4} | {:.4}", grid.vel_x_grid(x, y), grid.vel_x_grid(x + 1, y)));
+            ui.label(format!("y faces: {:.4} | {:.4}", grid.vel_y_grid(x, y), grid.vel_y_grid(x, y + 1)));
+            let vel = grid.vel(pos);
+            ui.label(format!("velocity ({:.4}, {:.4})", vel.x, vel.y));
+            ui.label(format!("temperature {:.4}", grid.temp(pos)));
+            ui.label(format!("dye {:.4}", grid.dye(pos)));
+
+            ui.separator();
+            for field in DerivedField::ALL {
+                ui.label(format!("{} {:.4}", field.name(), field.at(grid, &self.simulator.pressure, x, y)));
+            }
+        });
+    }
+
+    fn draw_probes(&self, painter: &Painter, to_screen: &RectTransform) {
+        for probe in &self.simulator.probes.probes {
+            let center = self.world_to_screen(to_screen, probe.pos);
+            let stroke = Stroke::new(1.5, Color32::from_rgb(0, 220, 120));
+            painter.line_segment([center - vec2(5.0, 0.0), center + vec2(5.0, 0.0)], stroke);
+            painter.line_segment([center - vec2(0.0, 5.0), center + vec2(0.0, 5.0)], stroke);
+            painter.text(center + vec2(6.0, -6.0), egui::Align2::LEFT_BOTTOM, &probe.name, egui::FontId::proportional(11.0), stroke.color);
+        }
+    }
+
+    fn handle_canvas_input(&mut self, response: &Response, painter: &Painter, to_screen: &RectTransform) {
+        // the other buttons move the view
+        if response.ctx.input(|i| i.pointer.button_down(PointerButton::Secondary) || i.pointer.button_down(PointerButton::Middle)) {
+            return;
+        }
+
+        let pointer = response.interact_pointer_pos().or(response.hover_pos());
+        let clicked = if response.clicked() { pointer.map(|p| self.screen_to_world(to_screen, p)) } else { None };
+
+        match self.canvas_tool {
+            CanvasTool::Seed => {
+                if let Some(pos) = clicked {
+                    self.flow_lines.add_seed(pos);
+                }
+            },
+            CanvasTool::Rake => {
+                if response.drag_started() {
+                    self.rake_start = pointer.map(|p| self.screen_to_world(to_screen, p));
+                }
+
+                if let (Some(start), Some(pointer)) = (self.rake_start, pointer) {
+                    let end = self.screen_to_world(to_screen, pointer);
+
+                    if response.drag_released() {
+                        self.flow_lines.add_rake(start, end, self.rake_seed_count);
+                        self.rake_start = None;
+                    } else {
+                        // preview
+                        let line = [self.world_to_screen(to_screen, start), pointer];
+                        painter.line_segment(line, Stroke::new(self.line_width * 2.0, Color32::RED));
+                    }
+                }
+            },
+            CanvasTool::Particles => {
+                if let Some(pos) = clicked {
+                    self.simulator.particles.add_cluster(pos, 0.5, self.particles_per_click);
+                }
+            },
+            CanvasTool::Emitter => {
+                if let Some(pos) = clicked {
+                    self.simulator.particles.emitters.push(Emitter::new(pos, self.emitter_rate, 0.5));
+                }
+            },
+            CanvasTool::Probe => {
+                if let Some(pos) = clicked {
+                    self.simulator.probes.add(pos);
+                }
+            },
+            CanvasTool::ObstacleRectangle | CanvasTool::ObstacleCircle | CanvasTool::ObstaclePolygon | CanvasTool::EditObstacles => {
+                self.handle_obstacle_input(response, to_screen);
+            },
+            tool => {
+                let Some(pointer) = pointer else { return };
+                let pos = self.screen_to_world(to_screen, pointer);
+
+                // brush outline
+                let radius = to_screen.scale().x * (self.brush.radius / self.simulator.grid.cell_count as f64) as f32;
+                painter.circle_stroke(pointer, radius, Stroke::new(self.line_width * 2.0, Color32::WHITE));
+
+                if !response.is_pointer_button_down_on() {
+                    return;
+                }
+
+                // real time covered by this frame, the pointer velocity (in cells per second) is the injected velocity
+                let dt = response.ctx.input(|i| i.stable_dt) as f64;
+                let drag = pos - self.screen_to_world(to_screen, pointer - response.drag_delta());
+
+                let grid = &mut self.simulator.grid;
+                match tool {
+                    CanvasTool::PaintVelocity => self.brush.paint_velocity(grid, pos, (1.0 / dt) * drag, dt),
+                    CanvasTool::PaintDye => self.brush.paint_dye(grid, pos, dt),
+                    CanvasTool::PaintTemperature => self.brush.paint_temperature(grid, pos, 1.0, dt),
+                    _ => self.brush.erase(grid, pos, dt)
+                }
+                self.lic_dirty = true;
+            }
+        }
+    }
+
+    fn diagnostics_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Diagnostics");
+
+        ui.add(Slider::new(&mut self.simulator.diagnostics.capacity, 10..=10000).logarithmic(true).text("History (steps)"));
+        if ui.button("Clear history").clicked() {
+            self.simulator.diagnostics.clear();
+        }
+
+        ui.collapsing("Plotted series", |ui| {
+            for series in DiagnosticSeries::ALL {
+                let mut plotted = self.plotted_diagnostics.contains(&series);
+                if ui.checkbox(&mut plotted, series.name()).changed() {
+                    self.plotted_diagnostics.retain(|s| *s != series);
+                    if plotted {
+                        self.plotted_diagnostics.push(series);
+                    }
+                }
+            }
+        });
+
+        for series in DiagnosticSeries::ALL.into_iter().filter(|s| self.plotted_diagnostics.contains(s)) {
+            let points = self.simulator.diagnostics.series(series);
+            plot::line_plot(ui, series.name(), &points, Color32::LIGHT_BLUE, 70.0);
+        }
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.export_path);
+            if ui.button("Export CSV").clicked() {
+                self.export_status = match std::fs::write(&self.export_path, self.simulator.diagnostics.to_csv()) {
+                    Ok(()) => format!("wrote {} steps to {}", self.simulator.diagnostics.samples().len(), self.export_path),
+                    Err(e) => format!("export failed: {e}")
+                };
+            }
+        });
+
+        ui.separator();
+        ui.heading("Probes");
+        ui.label("Place probes with the probe canvas tool");
+        egui::ComboBox::from_label("Probe quantity")
+            .selected_text(self.probe_quantity.name())
+            .show_ui(ui, |ui| {
+                for quantity in ProbeQuantity::ALL {
+                    ui.selectable_value(&mut self.probe_quantity, quantity, quantity.name());
+                }
+            });
+
+        let mut removed = None;
+        for (i, probe) in self.simulator.probes.probes.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut probe.name).desired_width(80.0));
+                ui.add(egui::DragValue::new(&mut probe.pos.x).speed(0.1).prefix("x "));
+                ui.add(egui::DragValue::new(&mut probe.pos.y).speed(0.1).prefix("y "));
+                if ui.small_button("x").on_hover_text(format!("remove ({} samples recorded)", probe.history().len())).clicked() {
+                    removed = Some(i);
+                }
+            });
+            plot::line_plot(ui, &format!("{} {}", probe.name, self.probe_quantity.name()), &probe.series(self.probe_quantity), Color32::from_rgb(0, 220, 120), 60.0);
+        }
+        if let Some(i) = removed {
+            self.simulator.probes.probes.remove(i);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Clear probe histories").clicked() {
+                self.simulator.probes.clear_history();
+            }
+            if ui.button("Remove all").clicked() {
+                self.simulator.probes.probes.clear();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.probe_export_path);
+            if ui.button("Export probes").clicked() {
+                self.export_status = match std::fs::write(&self.probe_export_path, self.simulator.probes.to_csv()) {
+                    Ok(()) => format!("wrote {} probes to {}", self.simulator.probes.probes.len(), self.probe_export_path),
+                    Err(e) => format!("export failed: {e}")
+                };
+            }
+        });
+
+        ui.separator();
+        ui.heading("VTK export");
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.vtk_path).on_hover_text(".vti for XML image data, anything else for legacy VTK");
+            if ui.button("Export grid").clicked() {
+                let path = std::path::PathBuf::from(&self.vtk_path);
+                self.export_status = match vtk::export(&self.simulator.grid, &self.simulator.pressure, self.simulator.time, &path) {
+                    Ok(()) => format!("wrote step {} to {}", self.simulator.current_time_step, self.vtk_path),
+                    Err(e) => format!("export failed: {e}")
+                };
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add_enabled(self.vtk_series.is_none(), egui::TextEdit::singleline(&mut self.vtk_series_directory));
+            let mut recording = self.vtk_series.is_some();
+            if ui.toggle_value(&mut recording, "Record .pvd series").changed() {
+                self.vtk_series = recording.then(|| VtkSeries::new(&self.vtk_series_directory, "flowy"));
+            }
+        });
+        if let Some(series) = &self.vtk_series {
+            ui.label(format!("{} steps in {}", series.entries().len(), series.collection_path().display()));
+        }
+
+        ui.separator();
+        ui.heading("Field data");
+        egui::ComboBox::from_label("Field")
+            .selected_text(self.array_field.name())
+            .show_ui(ui, |ui| {
+                for field in GridField::ALL {
+                    ui.selectable_value(&mut self.array_field, field, field.name());
+                }
+            });
+        let (rows, cols) = self.array_field.shape(self.simulator.grid.cell_count);
+        ui.label(format!("{rows} x {cols} array, indexed [y][x]"));
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.array_path).on_hover_text(".npy for NumPy arrays, anything else for CSV");
+            let path = std::path::PathBuf::from(&self.array_path);
+            if ui.button("Export").clicked() {
+                self.export_status = match self.array_field.export(&self.simulator.grid, &path) {
+                    Ok(()) => format!("wrote {} to {}", self.array_field.name(), self.array_path),
+                    Err(e) => format!("export failed: {e}")
+                };
+            }
+            if ui.button("Import").clicked() {
+                self.export_status = match self.array_field.import(&mut self.simulator.grid, &path) {
+                    Ok(()) => {
+                        self.lic_dirty = true;
+                        format!("imported {} from {}", self.array_field.name(), self.array_path)
+                    },
+                    Err(e) => format!("import failed: {e}")
+                };
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.npz_path);
+            let path = std::path::PathBuf::from(&self.npz_path);
+            if ui.button("Export .npz").clicked() {
+                self.export_status = match arrays::export_npz(&self.simulator.grid, &path) {
+                    Ok(()) => format!("wrote all fields to {}", self.npz_path),
+                    Err(e) => format!("export failed: {e}")
+                };
+            }
+            if ui.button("Import .npz").clicked() {
+                self.export_status = match arrays::import_npz(&mut self.simulator.grid, &path) {
+                    Ok(fields) => {
+                        self.lic_dirty = true;
+                        let names: Vec<&str> = fields.iter().map(|f| f.name()).collect();
+                        format!("imported {} from {}", names.join(", "), self.npz_path)
+                    },
+                    Err(e) => format!("import failed: {e}")
+                };
+            }
+        });
+
+        ui.separator();
+        ui.heading("Recording");
+        self.recording_ui(ui);
+
+        if !self.export_status.is_empty() {
+            ui.label(&self.export_status);
+        }
+    }
+
+    fn step(&mut self) {
+        self.simulator.step(self.dt);
+        if let Some(series) = &mut self.vtk_series {
+            if let Err(e) = series.write_step(&self.simulator.grid, &self.simulator.pressure, self.simulator.time, self.simulator.current_time_step) {
+                self.export_status = format!("VTK series stopped: {e}");
+                self.vtk_series = None;
+            }
+        }
+        self.flow_lines.step(&self.simulator.grid, self.dt);
+        self.lic_dirty = true;
+
+        if self.recorder.as_mut().is_some_and(|r| r.wants_frame()) {
+            let frame = self.layer_stack().render(&self.simulator, self.recording_size);
+            if let Err(e) = self.recorder.as_mut().unwrap().add_frame(&frame) {
+                self.export_status = format!("recording stopped: {e}");
+                self.recorder = None;
+            }
+        }
+    }
+
+    /// the visible canvas layers for offscreen rendering
+    fn layer_stack(&mut self) -> LayerStack {
+        let mut stack = LayerStack::new();
+        stack.speed = self.draw_speed.then(|| self.speed_scale.clone());
+        stack.temperature = self.draw_temperature.then(|| self.temperature_scale.clone());
+        stack.dye = self.draw_dye.then(|| self.dye_scale.clone());
+        stack.derived = self.draw_derived.then(|| (self.derived_field, self.derived_scale.clone()));
+        if self.draw_lic {
+            stack.lic = Some(self.lic.compute(&self.simulator.grid, &self.speed_scale));
+        }
+        stack.obstacles = self.draw_obstacles;
+        stack.upsampling = if self.upsample_fields { self.upsampling_factor } else { 1 };
+        stack.smooth = self.smooth_fields;
+
+        let grid = &self.simulator.grid;
+        if self.draw_streamlines {
+            stack.lines.extend(self.flow_lines.seeds().iter().map(|seed| (self.flow_lines.streamline(grid, *seed), Color32::WHITE)));
+        }
+        if self.draw_pathlines {
+            stack.lines.extend(self.flow_lines.pathlines().iter().map(|line| (line.iter().copied().collect(), Color32::YELLOW)));
+        }
+        if self.draw_streaklines {
+            stack.lines.extend(self.flow_lines.streaklines().iter().map(|line| (line.iter().copied().collect(), Color32::LIGHT_BLUE)));
+        }
+        if self.draw_particles {
+            stack.points = self.simulator.particles.particles.iter().map(|p| (p.pos, self.particle_color(p.pos))).collect();
+        }
+
+        stack
+    }
+
+    fn recording_ui(&mut self, ui: &mut egui::Ui) {
+        ui.add_enabled_ui(self.recorder.is_none(), |ui| {
+            egui::ComboBox::from_label("Format")
+                .selected_text(self.recording_format.name())
+                .show_ui(ui, |ui| {
+                    for format in RecordingFormat::ALL {
+                        ui.selectable_value(&mut self.recording_format, format, format.name());
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.label(if self.recording_format == RecordingFormat::Gif { "File" } else { "Directory" });
+                ui.text_edit_singleline(&mut self.recording_path);
+            });
+            ui.add(Slider::new(&mut self.recording_size, 64..=2048).logarithmic(true).text("Resolution (px)"));
+            ui.add(Slider::new(&mut self.recording_skip, 0..=50).text("Skipped steps per frame"));
+        });
+
+        let mut recording = self.recorder.is_some();
+        if ui.toggle_value(&mut recording, "Record").changed() {
+            if recording {
+                self.recorder = Some(Recorder::new(self.recording_format, &self.recording_path, self.recording_skip));
+            } else if let Some(mut recorder) = self.recorder.take() {
+                recorder.finish();
+                self.export_status = format!("recorded {} frames to {}", recorder.frames(), self.recording_path);
+            }
+        }
+        if let Some(recorder) = &self.recorder {
+            ui.label(format!("{} frames recorded", recorder.frames()));
+        }
+    }
+
+    /// forgets everything that refers to the previous grid, after it was replaced by a snapshot, checkpoint or scenario
+    fn grid_replaced(&mut self) {
+        self.selected_obstacle = None;
+        self.obstacle_drag = None;
+        self.obstacle_draft.clear();
+        self.flow_lines.reset_history();
+        self.lic_dirty = true;
+    }
+
+    fn restore_snapshot(&mut self, i: usize) {
+        self.snapshots[i].apply(&mut self.simulator);
+        self.dt = self.snapshots[i].dt;
+        self.simulator.pressure.clear();
+        self.grid_replaced();
     }
 
     fn take_snapshot(&mut self) {
-        self.snapshots.push(Snapshot::new(self.simulator.current_time_step, self.simulator.grid.clone()));
+        self.snapshots.push(Snapshot::new(&self.simulator, self.dt));
+    }
+
+    fn checkpoint_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Checkpoint");
+            if ui.text_edit_singleline(&mut self.checkpoint_path).changed() {
+                self.simulator.checkpoints.path = std::path::PathBuf::from(&self.checkpoint_path);
+            }
+        });
+        ui.add(egui::DragValue::new(&mut self.simulator.checkpoints.interval).prefix("every ").suffix(" steps (0 = off)"));
+        match &self.simulator.checkpoints.last {
+            Some(Ok(step)) => ui.label(format!("last checkpoint at step {step}")),
+            Some(Err(e)) => ui.colored_label(Color32::RED, format!("checkpoint failed: {e}")),
+            None => ui.label("no checkpoint written yet")
+        };
+
+        ui.horizontal(|ui| {
+            let path = self.simulator.checkpoints.path.clone();
+            if ui.button("Save checkpoint").clicked() {
+                self.checkpoint_status = match self.simulator.save_checkpoint(&path, self.dt) {
+                    Ok(()) => format!("saved step {} to {}", self.simulator.current_time_step, self.checkpoint_path),
+                    Err(e) => format!("save failed: {e}")
+                };
+            }
+            if ui.button("Resume").clicked() {
+                self.checkpoint_status = match self.simulator.restore_checkpoint(&path) {
+                    Ok(dt) => {
+                        self.dt = dt.unwrap_or(self.dt);
+                        self.grid_replaced();
+                        format!("resumed at step {}", self.simulator.current_time_step)
+                    },
+                    Err(e) => format!("resume failed: {e}")
+                };
+            }
+        });
+        if !self.checkpoint_status.is_empty() {
+            ui.label(&self.checkpoint_status);
+        }
+    }
+
+    /// replaces the simulation with the one described by a scenario, starting a new snapshot history
+    pub fn load_scenario(&mut self, scenario: &Scenario) -> Result<(), FormatError> {
+        self.simulator = scenario.build()?;
+        self.dt = scenario.simulation.dt;
+        self.checkpoint_path = self.simulator.checkpoints.path.display().to_string();
+
+        self.snapshots = vec![Snapshot::new(&self.simulator, self.dt)];
+        self.selected_snapshot = None;
+        self.flow_lines.clear();
+        self.vtk_series = None;
+        self.grid_replaced();
+        Ok(())
     }
+
+    fn scenario_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("builtin_scenario")
+                .selected_text(self.builtin_scenario.name())
+                .show_ui(ui, |ui| {
+                    for builtin in BuiltinScenario::ALL {
+                        ui.selectable_value(&mut self.builtin_scenario, builtin, builtin.name());
+                    }
+                });
+            if ui.button("Load built-in").clicked() {
+                let scenario = self.builtin_scenario.scenario();
+                self.scenario_status = match self.load_scenario(&scenario) {
+                    Ok(()) => scenario.description,
+                    Err(e) => format!("load failed: {e}")
+                };
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Scenario");
+            ui.text_edit_singleline(&mut self.scenario_path);
+        });
+        let path = std::path::PathBuf::from(&self.scenario_path);
+        ui.horizontal(|ui| {
+            if ui.button("Load scenario").clicked() {
+                self.scenario_status = match Scenario::load(&path).and_then(|scenario| self.load_scenario(&scenario).map(|_| scenario)) {
+                    Ok(scenario) => format!("loaded \"{}\"", scenario.name),
+                    Err(e) => format!("load failed: {e}")
+                };
+            }
+            // the setup only, the current fields go into snapshots
+            if ui.button("Save scenario").clicked() {
+                self.scenario_status = match Scenario::from_simulator(&self.simulator, self.dt).save(&path) {
+                    Ok(()) => format!("saved the setup to {}", path.display()),
+                    Err(e) => format!("save failed: {e}")
+                };
+            }
+        });
+        if !self.scenario_status.is_empty() {
+            ui.label(&self.scenario_status);
+        }
+
+        ui.label("Field from an expression in x and y (0 to 1 across the domain)");
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("expression_field")
+                .selected_text(self.expression_field.name())
+                .show_ui(ui, |ui| {
+                    for field in GridField::ALL {
+                        ui.selectable_value(&mut self.expression_field, field, field.name());
+                    }
+                });
+            ui.text_edit_singleline(&mut self.expression);
+        });
+        if ui.button("Apply expression").clicked() {
+            let result = Expression::parse(&self.expression).map_err(|e| e.to_string())
+                .and_then(|expression| expression.fill(&mut self.simulator.grid, self.expression_field));
+            self.expression_status = match result {
+                Ok(()) => {
+                    self.simulator.grid.enforce_obstacles();
+                    self.lic_dirty = true;
+                    format!("set the {}", self.expression_field.name())
+                },
+                Err(e) => e
+            };
+        }
+        if !self.expression_status.is_empty() {
+            ui.label(&self.expression_status);
+        }
+    }
+
+    fn snapshot_files_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("File");
+            ui.text_edit_singleline(&mut self.snapshot_path);
+        });
+
+        let path = std::path::PathBuf::from(&self.snapshot_path);
+        ui.horizontal(|ui| {
+            if ui.button("Save selected").clicked() {
+                let i = self.selected_snapshot.unwrap_or(self.snapshots.len() - 1);
+                self.snapshot_status = match self.snapshots[i].save(&path) {
+                    Ok(()) => format!("saved snapshots[{i}] to {}", self.snapshot_path),
+                    Err(e) => format!("save failed: {e}")
+                };
+            }
+            if ui.button("Save all").clicked() {
+                self.snapshot_status = match snapshot::save_collection(&self.snapshots, &path) {
+                    Ok(()) => format!("saved {} snapshots to {}", self.snapshots.len(), self.snapshot_path),
+                    Err(e) => format!("save failed: {e}")
+                };
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Load").clicked() {
+                self.snapshot_status = match Snapshot::load(&path) {
+                    Ok(snapshot) => {
+                        self.snapshots.push(snapshot);
+                        self.selected_snapshot = Some(self.snapshots.len() - 1);
+                        self.restore_snapshot(self.snapshots.len() - 1);
+                        format!("loaded {}", self.snapshot_path)
+                    },
+                    Err(e) => format!("load failed: {e}")
+                };
+            }
+            if ui.button("Load all").clicked() {
+                self.snapshot_status = match snapshot::load_collection(&path) {
+                    Ok(snapshots) if snapshots.is_empty() => String::from("the file contains no snapshots"),
+                    Ok(snapshots) => {
+                        let count = snapshots.len();
+                        self.snapshots = snapshots;
+                        self.selected_snapshot = Some(0);
+                        self.restore_snapshot(0);
+                        format!("loaded {count} snapshots from {}", self.snapshot_path)
+                    },
+                    Err(e) => format!("load failed: {e}")
+                };
+            }
+        });
+        if !self.snapshot_status.is_empty() {
+            ui.label(&self.snapshot_status);
+        }
+    }
+}
+
+fn interpolation_combo_box(ui: &mut egui::Ui, label: &str, selected: &mut Interpolation2DKind) {
+    egui::ComboBox::from_label(label)
+        .selected_text(selected.name())
+        .show_ui(ui, |ui| {
+            for kind in Interpolation2DKind::ALL {
+                ui.selectable_value(selected, kind, kind.name());
+            }
+        });
 }
 
 impl eframe::App for FlowyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::SidePanel::left("diagnostics_panel").resizable(true).default_width(260.0).show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| self.diagnostics_ui(ui));
+        });
+
         egui::SidePanel::right("settings_panel").show(ctx, |ui| {
             ui.heading("Settings");
 
             // main controls
             ui.label("Visualization parameters");
             ui.add(Slider::new(&mut self.line_width, 0.01..=5.0).text("Line width"));
-            ui.add(Slider::new(&mut self.vel_scaling_factor, 0.01..=10.0).text("Velocity scaling factor"));
-            ui.add(Slider::new(&mut self.temp_scaling_factor, 0.01..=10.0).text("Temperature scaling factor"));
+            ui.add(Slider::new(&mut self.vel_scaling_factor, 0.01..=10.0).text("Velocity vector scaling factor"));
 
+            ui.horizontal(|ui| {
+                if ui.button("Reset view").clicked() {
+                    self.view = DEFAULT_VIEW;
+                }
+                ui.checkbox(&mut self.inspect_cells, "Inspect cells on hover");
+            });
             ui.toggle_value(&mut self.draw_grid, "Draw grid");
-            ui.toggle_value(&mut self.draw_velocity_greyscale, "Draw velocity (greyscale)");
+            ui.toggle_value(&mut self.draw_speed, "Draw speed");
             ui.toggle_value(&mut self.draw_velocity_edge_vectors, "Draw velocity (edge vectors)");
             ui.toggle_value(&mut self.draw_velocity_center_vectors, "Draw velocity (center vectors)");
             ui.toggle_value(&mut self.draw_temperature, "Draw temperature");
+            ui.toggle_value(&mut self.draw_dye, "Draw dye");
+            ui.toggle_value(&mut self.draw_obstacles, "Draw obstacles");
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.smooth_fields, "Smooth fields");
+                ui.checkbox(&mut self.upsample_fields, "Upsample");
+            });
+            if self.upsample_fields {
+                ui.add(Slider::new(&mut self.upsampling_factor, 2..=8).text("Texels per cell"));
+            }
+
+            ui.horizontal(|ui| {
+                ui.toggle_value(&mut self.draw_derived, "Draw derived field");
+                egui::ComboBox::from_id_source("derived_field")
+                    .selected_text(self.derived_field.name())
+                    .show_ui(ui, |ui| {
+                        for field in DerivedField::ALL {
+                            ui.selectable_value(&mut self.derived_field, field, field.name());
+                        }
+                    });
+            });
+
+            ui.label("Speed color map");
+            self.lic_dirty |= self.speed_scale.ui(ui, "speed");
+            ui.label("Temperature color map");
+            self.temperature_scale.ui(ui, "temperature");
+            ui.label("Dye color map");
+            self.dye_scale.ui(ui, "dye");
+            ui.label("Derived field color map");
+            self.derived_scale.ui(ui, "derived");
+
+            ui.label("Line integral convolution");
+            ui.toggle_value(&mut self.draw_lic, "Draw LIC");
+            let lic_changed = ui.add(Slider::new(&mut self.lic.resolution, 64..=1024).text("LIC resolution")).changed()
+                | ui.add(Slider::new(&mut self.lic.kernel_length, 1..=50).text("LIC kernel length")).changed()
+                | ui.checkbox(&mut self.lic.color_by_speed, "Color LIC by speed").changed();
+            self.lic_dirty |= lic_changed;
+
+            ui.label("Flow lines");
+            ui.horizontal(|ui| {
+                ui.toggle_value(&mut self.draw_streamlines, "Streamlines");
+                ui.toggle_value(&mut self.draw_pathlines, "Pathlines");
+                ui.toggle_value(&mut self.draw_streaklines, "Streaklines");
+            });
+            ui.add(Slider::new(&mut self.rake_seed_count, 2..=50).text("Seeds per rake"));
+            ui.add(Slider::new(&mut self.flow_lines.history_length, 10..=1000).text("Path/streakline length (steps)"));
+            if ui.button("Clear seeds").clicked() {
+                self.flow_lines.clear();
+            }
+
+            ui.label("Tracer particles");
+            ui.toggle_value(&mut self.draw_particles, "Draw particles");
+            egui::ComboBox::from_label("Particle color")
+                .selected_text(self.particle_coloring.name())
+                .show_ui(ui, |ui| {
+                    for coloring in ParticleColoring::ALL {
+                        ui.selectable_value(&mut self.particle_coloring, coloring, coloring.name());
+                    }
+                });
+            ui.add(Slider::new(&mut self.particles_per_click, 1..=500).text("Particles per click"));
+            ui.add(Slider::new(&mut self.emitter_rate, 1.0..=200.0).text("Emitter rate (1/time)"));
+            ui.add(Slider::new(&mut self.simulator.particles.lifetime, 1.0..=500.0).text("Particle lifetime"));
+            ui.add(Slider::new(&mut self.simulator.particles.max_count, 100..=50000).text("Maximum particle count"));
+            ui.add(Slider::new(&mut self.simulator.particles.trail_length, 0..=100).text("Trail length (steps)"));
+            if ui.button("Clear particles").clicked() {
+                self.simulator.particles.clear();
+            }
+
+            egui::ComboBox::from_label("Canvas tool")
+                .selected_text(self.canvas_tool.name())
+                .show_ui(ui, |ui| {
+                    for tool in CanvasTool::ALL {
+                        ui.selectable_value(&mut self.canvas_tool, tool, tool.name());
+                    }
+                });
+            if self.canvas_tool == CanvasTool::EditObstacles {
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(self.selected_obstacle.is_some(), egui::Button::new("Delete obstacle")).clicked() {
+                        self.delete_selected_obstacle();
+                    }
+                    if ui.button("Clear obstacles").clicked() {
+                        self.simulator.grid.obstacles.clear();
+                        self.selected_obstacle = None;
+                        self.obstacles_changed();
+                    }
+                });
+            }
+            if self.canvas_tool.is_brush() {
+                ui.add(Slider::new(&mut self.brush.radius, 0.5..=20.0).text("Brush radius (cells)"));
+                ui.add(Slider::new(&mut self.brush.strength, 0.1..=50.0).logarithmic(true).text("Brush strength (1/s)"));
+            }
 
             ui.label("Simulation parameters");
+            // the cached LIC image samples the velocity field
+            let velocity_sampling = (self.simulator.grid.vel_interpolation, self.simulator.grid.boundary);
+            interpolation_combo_box(ui, "Velocity interpolation", &mut self.simulator.grid.vel_interpolation);
+            interpolation_combo_box(ui, "Temperature interpolation", &mut self.simulator.grid.temp_interpolation);
+
+            let boundary = &mut self.simulator.grid.boundary;
+            egui::ComboBox::from_label("Boundary")
+                .selected_text(boundary.name())
+                .show_ui(ui, |ui| {
+                    for policy in BoundaryPolicy::ALL {
+                        ui.selectable_value(boundary, policy, policy.name());
+                    }
+                });
+            self.lic_dirty |= velocity_sampling != (self.simulator.grid.vel_interpolation, self.simulator.grid.boundary);
             ui.add(Slider::new(&mut self.dt, 0.01..=10.0).text("Time step (ms)"));
+            ui.checkbox(&mut self.simulator.projection, "Pressure projection");
+            let forces = &mut self.simulator.forces;
+            ui.add(Slider::new(&mut forces.buoyancy, 0.0..=1.0).text("Buoyancy (per unit temperature)"));
+            ui.add(Slider::new(&mut forces.gravity.y, -1.0..=1.0).text("Gravity (downwards)"));
+            ui.horizontal(|ui| {
+                ui.label(format!("{} sources", forces.sources.len()));
+                if ui.add_enabled(!forces.sources.is_empty(), egui::Button::new("Clear sources")).clicked() {
+                    forces.sources.clear();
+                }
+            });
+            ui.add(Slider::new(&mut self.simulator.pressure_solver.max_iterations, 1..=5000).logarithmic(true).text("Max. solver iterations"));
+            let stats = self.simulator.projection_stats;
+            ui.label(format!("Last solve: {} iterations, residual {:.2e}", stats.iterations, stats.residual));
             ui.add(Slider::new(&mut self.ticks_per_second, 1..=100).text("Simulation speed (t/s)"));
             ui.toggle_value(&mut self.simulation_running, format!("Run simulation at {} t/second", self.ticks_per_second));
 
@@ -201,7 +1449,7 @@ impl eframe::App for FlowyApp {
                 let tick_dt = (1000.0 / self.ticks_per_second as f64) as i64;
                 if now.signed_duration_since(self.simulator.last_stepped).num_milliseconds() > tick_dt {
                     // step
-                    self.simulator.advect(self.dt);
+                    self.step();
                 }
 
                 if self.simulation_running {
@@ -220,24 +1468,35 @@ impl eframe::App for FlowyApp {
             };
 
             // snapshots
+            let mut restored = None;
             egui::ComboBox::from_label("Select a snapshot to restore")
                 .selected_text(snapshot_selection_text)
                 .show_ui(ui, |ui| {
                     for (i, snapshot) in self.snapshots.iter().enumerate() {
                         let text = format!("[{}]: timestep={}", i, snapshot.timestep);
                         if ui.selectable_value(&mut self.selected_snapshot, Some(i), text).clicked() {
-                            // restore snapshot
-                            self.simulator.grid = snapshot.grid.clone();
+                            restored = Some(i);
                         }
                     }
                 });
+            // restore snapshot
+            if let Some(i) = restored {
+                self.restore_snapshot(i);
+            }
 
             if ui.button("Restore").clicked() {
                 // restore snapshot (in case the user wants to restore the snapshot multiple times)
                 if let Some(i) = self.selected_snapshot {
-                    self.simulator.grid = self.snapshots[i].grid.clone();
+                    self.restore_snapshot(i);
                 }
             }
+            self.snapshot_files_ui(ui);
+
+            ui.separator();
+            self.checkpoint_ui(ui);
+
+            ui.separator();
+            self.scenario_ui(ui);
 
             ui.separator();
 
@@ -250,12 +1509,31 @@ impl eframe::App for FlowyApp {
 
             let w = ui.available_width();
             let h = ui.available_height();
-            let (response, painter) = ui.allocate_painter(Vec2::new(w, h), Sense::hover());
+            let (response, painter) = ui.allocate_painter(Vec2::new(w, h), Sense::click_and_drag());
+
+            self.handle_view_input(&response);
+            let to_screen = RectTransform::from_to(self.view, response.rect);
+
+            self.update_color_ranges();
+
+            if self.draw_speed {
+                self.draw_grid_speed(ctx, &painter, &to_screen);
+            }
+
+            if self.draw_temperature {
+                self.draw_grid_temperature(ctx, &painter, &to_screen);
+            }
 
-            let to_screen = RectTransform::from_to(Rect { min: pos2(-0.02, -0.02), max: pos2(1.02, 1.02) }, response.rect);
+            if self.draw_dye {
+                self.draw_grid_dye(ctx, &painter, &to_screen);
+            }
+
+            if self.draw_derived {
+                self.draw_grid_derived(ctx, &painter, &to_screen);
+            }
 
-            if self.draw_velocity_greyscale {
-                self.draw_grid_velocities_greyscale(&painter, &to_screen);
+            if self.draw_lic {
+                self.draw_grid_lic(ctx, &painter, &to_screen);
             }
 
             if self.draw_velocity_edge_vectors {
@@ -270,8 +1548,23 @@ impl eframe::App for FlowyApp {
                 self.draw_grid_lines(&painter, &to_screen);
             }
 
-            if self.draw_temperature {
-                self.draw_grid_temperature(&painter, &to_screen);
+            if self.draw_obstacles || self.canvas_tool == CanvasTool::EditObstacles || !self.obstacle_draft.is_empty() {
+                self.draw_grid_obstacles(ctx, &painter, &to_screen);
+            }
+
+            self.draw_flow_lines(&painter, &to_screen);
+            self.draw_probes(&painter, &to_screen);
+
+            if self.draw_particles {
+                self.draw_particles(&painter, &to_screen);
+            }
+
+            self.draw_color_bars(&painter, response.rect);
+
+            self.handle_canvas_input(&response, &painter, &to_screen);
+
+            if self.inspect_cells {
+                self.show_cell_inspector(&response, &to_screen);
             }
         });
     }
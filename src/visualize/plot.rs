@@ -0,0 +1,54 @@
+use egui::{Align2, FontId, Sense, Ui};
+use epaint::{Color32, Rect, Rounding, Shape, Stroke, pos2, vec2};
+
+/// line plot of `points` (x ascending) filling the available width, labelled with
+/// the title, the latest value and the value range; hovering shows the nearest point
+pub fn line_plot(ui: &mut Ui, title: &str, points: &[(f64, f64)], color: Color32, height: f32) {
+    let (rect, response) = ui.allocate_exact_size(vec2(ui.available_width(), height), Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, Rounding::same(2.0), Color32::from_gray(20));
+
+    let font = FontId::proportional(10.0);
+    let latest = points.last().map_or(String::from("-"), |(_, y)| format!("{y:.4e}"));
+    painter.text(rect.left_top() + vec2(3.0, 2.0), Align2::LEFT_TOP, format!("{title}: {latest}"), font.clone(), Color32::LIGHT_GRAY);
+
+    if points.is_empty() {
+        return;
+    }
+
+    let (x_min, x_max) = points.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), (x, _)| (lo.min(*x), hi.max(*x)));
+    let (y_min, y_max) = points.iter()
+        .filter(|(_, y)| y.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), (_, y)| (lo.min(*y), hi.max(*y)));
+    if y_min > y_max {
+        return;
+    }
+
+    // leave room for the labels, flat or single-point series are centered
+    let area = Rect::from_min_max(rect.min + vec2(2.0, 14.0), rect.max - vec2(2.0, 12.0));
+    let to_screen = |x: f64, y: f64| {
+        let tx = if x_max > x_min { (x - x_min) / (x_max - x_min) } else { 0.5 };
+        let ty = if y_max > y_min { (y - y_min) / (y_max - y_min) } else { 0.5 };
+        pos2(area.min.x + tx as f32 * area.width(), area.max.y - ty as f32 * area.height())
+    };
+
+    let line = points.iter().filter(|(_, y)| y.is_finite()).map(|(x, y)| to_screen(*x, *y)).collect();
+    painter.add(Shape::line(line, Stroke::new(1.0, color)));
+
+    painter.text(rect.right_top() + vec2(-3.0, 2.0), Align2::RIGHT_TOP, format!("max {y_max:.3e}"), font.clone(), Color32::GRAY);
+    painter.text(rect.right_bottom() + vec2(-3.0, -2.0), Align2::RIGHT_BOTTOM, format!("min {y_min:.3e}"), font.clone(), Color32::GRAY);
+    painter.text(rect.left_bottom() + vec2(3.0, -2.0), Align2::LEFT_BOTTOM, format!("t {x_min:.2} .. {x_max:.2}"), font, Color32::GRAY);
+
+    if let Some(pointer) = response.hover_pos() {
+        let nearest = points.iter().min_by(|a, b| {
+            let da = (to_screen(a.0, a.1).x - pointer.x).abs();
+            let db = (to_screen(b.0, b.1).x - pointer.x).abs();
+            da.total_cmp(&db)
+        });
+
+        if let Some(&(x, y)) = nearest {
+            painter.circle_filled(to_screen(x, y), 3.0, color);
+            response.on_hover_text(format!("t = {x:.3}\n{title} = {y:.6e}"));
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::{fs::{self, File}, io::{self, BufWriter}, path::PathBuf};
+
+use epaint::ColorImage;
+
+use crate::simulator::simulator::Simulator;
+
+use super::render::LayerStack;
+
+/// delay between animation frames in hundredths of a second
+const GIF_FRAME_DELAY: u16 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// numbered PNG images in a directory
+    PngFrames,
+    /// one looping animated GIF
+    Gif
+}
+
+impl RecordingFormat {
+    pub const ALL: [RecordingFormat; 2] = [Self::PngFrames, Self::Gif];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PngFrames => "PNG frames",
+            Self::Gif => "animated GIF"
+        }
+    }
+}
+
+/// writes rendered frames of every `frame_skip + 1`-th step
+pub struct Recorder {
+    pub format: RecordingFormat,
+    /// directory for PNG frames, file for GIFs
+    pub path: PathBuf,
+    pub frame_skip: u32,
+    steps: u32,
+    frames: usize,
+    gif: Option<gif::Encoder<BufWriter<File>>>
+}
+
+impl Recorder {
+    pub fn new(format: RecordingFormat, path: impl Into<PathBuf>, frame_skip: u32) -> Self {
+        Self { format, path: path.into(), frame_skip, steps: 0, frames: 0, gif: None }
+    }
+
+    /// call once per step, true if the step should be recorded
+    pub fn wants_frame(&mut self) -> bool {
+        let due = self.steps.is_multiple_of(self.frame_skip + 1);
+        self.steps += 1;
+        due
+    }
+
+    /// number of frames written so far
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn add_frame(&mut self, image: &ColorImage) -> io::Result<()> {
+        let [width, height] = image.size;
+        // frames are opaque, so premultiplied and straight alpha agree
+        let mut rgba = image.as_raw().to_vec();
+
+        match self.format {
+            RecordingFormat::PngFrames => {
+                fs::create_dir_all(&self.path)?;
+                let file = File::create(self.path.join(format!("frame_{:05}.png", self.frames)))?;
+                let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.write_header()?.write_image_data(&rgba)?;
+            },
+            RecordingFormat::Gif => {
+                if self.gif.is_none() {
+                    if let Some(parent) = self.path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    let file = BufWriter::new(File::create(&self.path)?);
+                    let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[]).map_err(io::Error::other)?;
+                    encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+                    self.gif = Some(encoder);
+                }
+
+                let mut frame = gif::Frame::from_rgba_speed(width as u16, height as u16, &mut rgba, 10);
+                frame.delay = GIF_FRAME_DELAY;
+                self.gif.as_mut().unwrap().write_frame(&frame).map_err(io::Error::other)?;
+            }
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// completes the GIF; dropping the recorder does the same
+    pub fn finish(&mut self) {
+        self.gif = None;
+    }
+}
+
+/// steps the simulation and records frames of `layers` without a window
+pub fn record_headless(simulator: &mut Simulator, dt: f64, steps: u32, layers: &LayerStack, size: usize, recorder: &mut Recorder) -> io::Result<()> {
+    for _ in 0..steps {
+        simulator.step(dt);
+        if recorder.wants_frame() {
+            recorder.add_frame(&layers.render(simulator, size))?;
+        }
+    }
+
+    recorder.finish();
+    Ok(())
+}
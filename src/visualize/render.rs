@@ -0,0 +1,181 @@
+use epaint::{Color32, ColorImage};
+
+use crate::simulator::{simulator::Simulator, grid::StaggeredMACGrid, derived::DerivedField, math::{vector2, Vector2}};
+
+use super::colormap::ColorScale;
+
+/// the canvas layers drawn into offscreen frames, bottom to top; works without a window
+#[derive(Clone)]
+pub struct LayerStack {
+    pub background: Color32,
+    pub speed: Option<ColorScale>,
+    pub temperature: Option<ColorScale>,
+    pub dye: Option<ColorScale>,
+    pub derived: Option<(DerivedField, ColorScale)>,
+    /// line integral convolution image covering the domain
+    pub lic: Option<ColorImage>,
+    pub obstacles: bool,
+    /// polylines in world space
+    pub lines: Vec<(Vec<Vector2>, Color32)>,
+    /// dots in world space, e.g. particles
+    pub points: Vec<(Vector2, Color32)>,
+    /// samples per cell of the scalar fields, sampled through the grid interpolation when above one
+    pub upsampling: usize,
+    /// filter linearly between samples
+    pub smooth: bool
+}
+
+impl Default for LayerStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LayerStack {
+    pub fn new() -> Self {
+        Self {
+            background: Color32::from_gray(27),
+            speed: None,
+            temperature: None,
+            dye: None,
+            derived: None,
+            lic: None,
+            obstacles: true,
+            lines: Vec::new(),
+            points: Vec::new(),
+            upsampling: 1,
+            smooth: false
+        }
+    }
+
+    /// the whole domain as a `size`² image; automatic color ranges follow the current fields
+    pub fn render(&self, simulator: &Simulator, size: usize) -> ColorImage {
+        let grid = &simulator.grid;
+        let cc = grid.cell_count as usize;
+        let resolution = (cc * self.upsampling.max(1)).max(cc);
+        let mut image = ColorImage::new([size, size], self.background);
+
+        let scalar_layer = |scale: &ColorScale, cells: Vec<f64>, batch: fn(&StaggeredMACGrid, &[Vector2], &mut [f64])| {
+            let mut scale = scale.clone();
+            scale.update_range(cells.iter().copied());
+            let values = if resolution == cc {
+                cells
+            } else {
+                let positions = grid.raster_positions(resolution);
+                let mut values = vec![0.0; positions.len()];
+                batch(grid, &positions, &mut values);
+                values
+            };
+            let pixels = values.iter().map(|v| scale.color(*v)).collect();
+            ColorImage { size: [resolution, resolution], pixels }
+        };
+
+        let mut rasters = Vec::new();
+        if let Some(scale) = &self.speed {
+            let speed_batch: fn(&StaggeredMACGrid, &[Vector2], &mut [f64]) = |grid, positions, out| {
+                let mut velocities = vec![vector2(0.0, 0.0); positions.len()];
+                grid.vel_batch(positions, &mut velocities);
+                out.iter_mut().zip(&velocities).for_each(|(o, v)| *o = v.len());
+            };
+            let speeds = grid.cell_centered_velocities().iter().map(|v| v.len()).collect();
+            rasters.push((scalar_layer(scale, speeds, speed_batch), self.smooth));
+        }
+        if let Some(scale) = &self.temperature {
+            rasters.push((scalar_layer(scale, grid.cell_temperatures(), StaggeredMACGrid::temp_batch), self.smooth));
+        }
+        if let Some(scale) = &self.dye {
+            rasters.push((scalar_layer(scale, grid.cell_dye(), StaggeredMACGrid::dye_batch), self.smooth));
+        }
+        if let Some((field, scale)) = &self.derived {
+            // derived fields are only available per cell
+            let values = field.compute(grid, &simulator.pressure);
+            let mut scale = scale.clone();
+            scale.update_range(values.iter().copied());
+            let pixels = values.iter().map(|v| scale.color(*v)).collect();
+            rasters.push((ColorImage { size: [cc, cc], pixels }, self.smooth));
+        }
+        if let Some(lic) = &self.lic {
+            rasters.push((lic.clone(), true));
+        }
+        if self.obstacles {
+            let solid = Color32::from_rgba_unmultiplied(90, 90, 90, 230);
+            let pixels = (0..cc * cc).map(|i| if grid.is_solid((i % cc) as i32, (i / cc) as i32) { solid } else { Color32::TRANSPARENT }).collect();
+            rasters.push((ColorImage { size: [cc, cc], pixels }, false));
+        }
+
+        for (raster, smooth) in &rasters {
+            for y in 0..size {
+                for x in 0..size {
+                    let u = (x as f32 + 0.5) / size as f32;
+                    let v = (y as f32 + 0.5) / size as f32;
+                    let color = sample(raster, u, v, *smooth);
+                    blend(&mut image, x as i32, y as i32, color);
+                }
+            }
+        }
+
+        // world space to pixels
+        let scale = size as f64 / cc as f64;
+        for (line, color) in &self.lines {
+            for pair in line.windows(2) {
+                draw_segment(&mut image, scale * pair[0], scale * pair[1], *color);
+            }
+        }
+        for (pos, color) in &self.points {
+            let center = scale * *pos;
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    blend(&mut image, center.x.floor() as i32 + dx, center.y.floor() as i32 + dy, *color);
+                }
+            }
+        }
+
+        image
+    }
+}
+
+/// `image` stretched over [0, 1]², nearest or bilinear with clamped edges like the canvas textures
+fn sample(image: &ColorImage, u: f32, v: f32, smooth: bool) -> Color32 {
+    let [w, h] = image.size;
+    let texel = |x: usize, y: usize| image.pixels[x.min(w - 1) + y.min(h - 1) * w];
+
+    if !smooth {
+        return texel((u * w as f32) as usize, (v * h as f32) as usize);
+    }
+
+    let (x, y) = ((u * w as f32 - 0.5).max(0.0), (v * h as f32 - 0.5).max(0.0));
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (sx, sy) = (x.fract(), y.fract());
+    let lerp = |a: Color32, b: Color32, s: f32| {
+        let channel = |i: usize| (a[i] as f32 + s * (b[i] as f32 - a[i] as f32)).round() as u8;
+        Color32::from_rgba_premultiplied(channel(0), channel(1), channel(2), channel(3))
+    };
+    lerp(lerp(texel(x0, y0), texel(x0 + 1, y0), sx), lerp(texel(x0, y0 + 1), texel(x0 + 1, y0 + 1), sx), sy)
+}
+
+/// premultiplied "over" compositing of one pixel, ignoring pixels outside the image
+fn blend(image: &mut ColorImage, x: i32, y: i32, color: Color32) {
+    let [w, h] = image.size;
+    if x < 0 || y < 0 || x >= w as i32 || y >= h as i32 {
+        return;
+    }
+
+    let dst = &mut image.pixels[x as usize + y as usize * w];
+    let keep = 255 - color.a() as u32;
+    let channel = |i: usize| (color[i] as u32 + (dst[i] as u32 * keep + 127) / 255).min(255) as u8;
+    *dst = Color32::from_rgba_premultiplied(channel(0), channel(1), channel(2), channel(3));
+}
+
+/// one pixel wide line between two pixel positions
+fn draw_segment(image: &mut ColorImage, a: Vector2, b: Vector2, color: Color32) {
+    let steps = (b - a).x.abs().max((b - a).y.abs()).ceil().max(1.0) as usize;
+    if steps > 4 * (image.size[0] + image.size[1]) {
+        // diverged positions
+        return;
+    }
+
+    for i in 0..=steps {
+        let p = a + (i as f64 / steps as f64) * (b - a);
+        blend(image, p.x.floor() as i32, p.y.floor() as i32, color);
+    }
+}